@@ -2,21 +2,34 @@ use std::error::Error;
 use std::{
     collections::HashMap,
     fmt::{Display, Formatter},
+    future::Future,
     hash::Hash,
+    sync::Arc,
 };
 
 use async_trait::async_trait;
-use tokio::sync::Mutex;
+use rand::Rng;
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{info, warn};
 
 use self::{
-    requests::{make_request, BungieRequest, BungieResponseError},
+    requests::{make_authorized_request, make_request, BungieRequest, BungieResponseError},
     responses::{
-        ActivityInfo, BungieProfile, CharacterActivityHistory, ProfileCurrentActivities,
-        ProfileInfo, PostGameCarnageReport,
+        ActivityInfo, ApiActivityDefinition, ApiNamedDefinition, BungieProfile, CareerStats, CharacterActivityHistory,
+        DestinyManifest, LinkedProfiles, PostGameCarnageReport, ProfileCharacters,
+        ProfileCurrentActivities, ProfileInfo, TrackableActivity, UserMemberships,
+    },
+};
+use crate::{
+    cache::DefinitionCache,
+    config::profiles::Profile,
+    consts::{
+        DUNGEON_ACTIVITY_MODE, RAID_ACTIVITY_MODE, RETRY_JITTER_RANGE, TRANSIENT_RETRY_ATTEMPTS,
+        TRANSIENT_RETRY_BASE_DELAY,
     },
 };
-use crate::config::profiles::Profile;
 
+pub mod auth;
 pub mod requests;
 pub mod responses;
 
@@ -39,41 +52,96 @@ impl Display for ApiError {
 
 impl Error for ApiError {}
 
-#[async_trait]
-pub trait Source<K: Hash + Eq + Clone + Send + Sync, V: Clone + Send> {
-    async fn get(&mut self, key: &K) -> Result<V, ApiError>
-    where
-        K: 'async_trait,
-    {
-        let cache = self.cache();
-
-        if cache.contains_key(&key) {
-            return Ok(cache.get(&key).unwrap().clone());
+impl ApiError {
+    pub fn is_throttling(&self) -> bool {
+        match self {
+            ApiError::ResponseError(e) => e.is_throttling(),
+            ApiError::ResponseDeserializeError(_) => false,
         }
+    }
 
-        let value_fut = Self::get_value(key.clone());
-
-        let value = value_fut.await?;
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::ResponseError(e) => e.is_transient(),
+            ApiError::ResponseDeserializeError(_) => false,
+        }
+    }
+}
 
-        cache.insert(key.clone(), value.clone());
+/// Sends `request` again after a jittered exponential backoff while it fails transiently.
+/// Anything else, or running out of attempts, hands back the last error.
+pub async fn retry_transient<T, F, Fut>(mut request: F) -> Result<T, ApiError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ApiError>>,
+{
+    let mut attempt = 1;
+
+    loop {
+        match request().await {
+            Err(e) if e.is_transient() && attempt < TRANSIENT_RETRY_ATTEMPTS => {
+                let jitter = rand::thread_rng().gen_range(RETRY_JITTER_RANGE);
+                let backoff = TRANSIENT_RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+
+                warn!(target: "api", "⚠️ API: Attempt {attempt} failed transiently, retrying: {e}");
+                tokio::time::sleep(backoff.mul_f64(jitter)).await;
+
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
 
-        Ok(value)
+#[async_trait]
+pub trait Source<K, V>: Sync
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    async fn get(&self, key: &K) -> Result<V, ApiError>
+    where
+        K: 'async_trait,
+    {
+        // Concurrent gets for the same key share a single cell, so only one request is made
+        let cell = self
+            .cache()
+            .lock()
+            .await
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let value = cell
+            .get_or_try_init(|| async {
+                let value = Self::get_value(key.clone()).await?;
+                self.on_fetched(key, &value).await;
+                Ok::<V, ApiError>(value)
+            })
+            .await?;
+
+        Ok(value.clone())
     }
 
     async fn get_value(key: K) -> Result<V, ApiError>;
 
-    fn cache(&mut self) -> &mut HashMap<K, V>;
+    async fn on_fetched(&self, _key: &K, _value: &V) {}
+
+    fn cache(&self) -> &Mutex<HashMap<K, Arc<OnceCell<V>>>>;
 }
 
 #[derive(Default)]
 pub struct ProfileInfoSource {
-    cache: HashMap<Profile, ProfileInfo>,
+    cache: Mutex<HashMap<Profile, Arc<OnceCell<ProfileInfo>>>>,
 }
 
 impl ProfileInfoSource {
-    pub fn set_characters(&mut self, profile: &Profile, characters: Vec<String>) {
-        if let Some(p) = self.cache.get_mut(profile) {
+    pub async fn set_characters(&self, profile: &Profile, characters: Vec<String>) {
+        let mut cache = self.cache.lock().await;
+
+        if let Some(mut p) = cache.get(profile).and_then(|c| c.get()).cloned() {
             p.character_ids = characters;
+            cache.insert(profile.clone(), Arc::new(OnceCell::from(p)));
         }
     }
 }
@@ -81,25 +149,128 @@ impl ProfileInfoSource {
 #[async_trait]
 impl Source<Profile, ProfileInfo> for ProfileInfoSource {
     async fn get_value(profile: Profile) -> Result<ProfileInfo, ApiError> {
-        let res_val = make_request(BungieRequest::GetProfile {
-            membership_type: profile.account_platform,
-            membership_id: &profile.account_id,
-            component: 100,
+        let res_val = retry_transient(|| async {
+            make_request(BungieRequest::GetProfile {
+                membership_type: profile.account_platform,
+                membership_id: &profile.account_id,
+                components: &[100],
+            })
+            .await
+            .map_err(|e| ApiError::ResponseError(e))
         })
-        .await
-        .map_err(|e| ApiError::ResponseError(e))?;
+        .await?;
 
         serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
     }
 
-    fn cache(&mut self) -> &mut HashMap<Profile, ProfileInfo> {
-        &mut self.cache
+    fn cache(&self) -> &Mutex<HashMap<Profile, Arc<OnceCell<ProfileInfo>>>> {
+        &self.cache
     }
 }
 
 #[derive(Default)]
 pub struct ActivityInfoSource {
-    cache: HashMap<usize, ActivityInfo>,
+    cache: Mutex<HashMap<usize, Arc<OnceCell<ActivityInfo>>>>,
+    store: Mutex<DefinitionCache>,
+}
+
+impl ActivityInfoSource {
+    pub fn new(store: DefinitionCache) -> Self {
+        let cache = store
+            .activities
+            .iter()
+            .map(|(hash, info)| (*hash, Arc::new(OnceCell::from(info.clone()))))
+            .collect();
+
+        Self {
+            cache: Mutex::new(cache),
+            store: Mutex::new(store),
+        }
+    }
+
+    /// Drops stored definitions if Bungie has published a new manifest since they were fetched.
+    pub async fn sync_manifest_version(&self) -> Result<(), ApiError> {
+        let version = Api::get_manifest_version().await?;
+        let mut store = self.store.lock().await;
+
+        if store.manifest_version.as_ref() == Some(&version) {
+            return Ok(());
+        }
+
+        if store.manifest_version.is_some() {
+            info!(target: "definitions", "🗑️ Definitions: Manifest changed to {}, invalidating stored definitions", version);
+
+            store.activities.clear();
+            self.cache.lock().await.clear();
+        }
+
+        store.manifest_version = Some(version);
+        store.save_in_background();
+
+        Ok(())
+    }
+
+    /// Raids and dungeons among the stored definitions, grouped by family, raids first.
+    /// Every activity in a fetched history has been looked up, so this covers what was played.
+    pub async fn get_trackable_activities(&self) -> Vec<TrackableActivity> {
+        let store = self.store.lock().await;
+        let mut by_family: HashMap<String, TrackableActivity> = HashMap::new();
+
+        for (hash, info) in store.activities.iter() {
+            if info.name.is_empty() || !(info.is_raid() || info.is_dungeon()) {
+                continue;
+            }
+
+            let activity = by_family
+                .entry(info.family_key())
+                .or_insert_with(|| TrackableActivity {
+                    name: info.name.clone(),
+                    is_raid: info.is_raid(),
+                    hashes: Vec::new(),
+                });
+
+            // The plain name, without a difficulty suffix
+            if info.name.len() < activity.name.len() {
+                activity.name = info.name.clone();
+            }
+
+            activity.hashes.push(*hash);
+        }
+
+        let mut activities: Vec<TrackableActivity> = by_family.into_values().collect();
+
+        for activity in activities.iter_mut() {
+            activity.hashes.sort();
+        }
+
+        activities.sort_by(|a, b| b.is_raid.cmp(&a.is_raid).then_with(|| a.name.cmp(&b.name)));
+        activities
+    }
+
+    /// Maps each stored raid and dungeon definition to the lowest hash in its family, so stats
+    /// for reissues of the same activity can be grouped together
+    pub async fn get_activity_families(&self) -> HashMap<usize, usize> {
+        let store = self.store.lock().await;
+
+        let families: Vec<(usize, String)> = store
+            .activities
+            .iter()
+            .filter(|(_, info)| !info.name.is_empty() && (info.is_raid() || info.is_dungeon()))
+            .map(|(hash, info)| (*hash, info.family_key()))
+            .collect();
+
+        let mut canonical: HashMap<&str, usize> = HashMap::new();
+
+        for (hash, key) in families.iter() {
+            let entry = canonical.entry(key.as_str()).or_insert(*hash);
+            *entry = (*entry).min(*hash);
+        }
+
+        families
+            .iter()
+            .map(|(hash, key)| (*hash, canonical[key.as_str()]))
+            .collect()
+    }
 }
 
 #[async_trait]
@@ -109,21 +280,89 @@ impl Source<usize, ActivityInfo> for ActivityInfoSource {
             .await
             .map_err(|e| ApiError::ResponseError(e))?;
 
-        serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
+        let definition = serde_json::from_value::<ApiActivityDefinition>(res_val)
+            .map_err(|e| ApiError::ResponseDeserializeError(e))?;
+
+        let (destination_hash, place_hash) = (definition.destination_hash, definition.place_hash);
+        let mut info: ActivityInfo = definition.into();
+
+        // Nice to have, so a failed lookup leaves them empty rather than failing the activity
+        if destination_hash != 0 {
+            info.destination = get_definition_name(BungieRequest::GetDestinyDestinationDefinition {
+                destination_hash,
+            })
+            .await;
+        }
+
+        if place_hash != 0 {
+            info.place =
+                get_definition_name(BungieRequest::GetDestinyPlaceDefinition { place_hash }).await;
+        }
+
+        Ok(info)
     }
 
-    fn cache(&mut self) -> &mut HashMap<usize, ActivityInfo> {
-        &mut self.cache
+    async fn on_fetched(&self, activity_hash: &usize, info: &ActivityInfo) {
+        let mut store = self.store.lock().await;
+        store.activities.insert(*activity_hash, info.clone());
+        store.save_in_background();
+    }
+
+    fn cache(&self) -> &Mutex<HashMap<usize, Arc<OnceCell<ActivityInfo>>>> {
+        &self.cache
+    }
+}
+
+async fn get_definition_name(req: BungieRequest<'_>) -> Option<String> {
+    let res_val = make_request(req).await.ok()?;
+
+    serde_json::from_value::<ApiNamedDefinition>(res_val).ok()?.into_name()
+}
+
+/// Reports keyed by instance id. A finished activity's report never changes, so entries are
+/// kept for the session.
+#[derive(Default)]
+pub struct PgcrSource {
+    cache: Mutex<HashMap<String, Arc<OnceCell<PostGameCarnageReport>>>>,
+}
+
+#[async_trait]
+impl Source<String, PostGameCarnageReport> for PgcrSource {
+    async fn get_value(instance_id: String) -> Result<PostGameCarnageReport, ApiError> {
+        Api::get_pgcr(&instance_id).await
+    }
+
+    fn cache(&self) -> &Mutex<HashMap<String, Arc<OnceCell<PostGameCarnageReport>>>> {
+        &self.cache
     }
 }
 
 #[derive(Default)]
 pub struct Api {
-    pub profile_info_source: Mutex<ProfileInfoSource>,
-    pub activity_info_source: Mutex<ActivityInfoSource>,
+    pub profile_info_source: ProfileInfoSource,
+    pub activity_info_source: ActivityInfoSource,
+    pub pgcr_source: PgcrSource,
 }
 
 impl Api {
+    pub fn new(definitions: DefinitionCache) -> Self {
+        Self {
+            profile_info_source: ProfileInfoSource::default(),
+            activity_info_source: ActivityInfoSource::new(definitions),
+            pgcr_source: PgcrSource::default(),
+        }
+    }
+
+    pub async fn get_manifest_version() -> Result<String, ApiError> {
+        let res_val = make_request(BungieRequest::GetDestinyManifest)
+            .await
+            .map_err(|e| ApiError::ResponseError(e))?;
+
+        serde_json::from_value::<DestinyManifest>(res_val)
+            .map(|m| m.version)
+            .map_err(|e| ApiError::ResponseDeserializeError(e))
+    }
+
     pub async fn search_profile(
         display_name: &String,
         display_name_code: usize,
@@ -138,13 +377,77 @@ impl Api {
         serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
     }
 
+    pub async fn search_profile_legacy(
+        display_name: &str,
+        membership_type: isize,
+    ) -> Result<Vec<BungieProfile>, ApiError> {
+        let res_val = make_request(BungieRequest::SearchDestinyPlayer {
+            membership_type,
+            display_name,
+        })
+        .await
+        .map_err(|e| ApiError::ResponseError(e))?;
+
+        serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
+    }
+
+    /// The cross-save primary membership for the account, or `None` if cross-save isn't set up
+    pub async fn get_linked_profiles(
+        membership_type: usize,
+        membership_id: &str,
+    ) -> Result<Option<Profile>, ApiError> {
+        let res_val = make_request(BungieRequest::GetLinkedProfiles {
+            membership_type,
+            membership_id,
+        })
+        .await
+        .map_err(|e| ApiError::ResponseError(e))?;
+
+        let linked: LinkedProfiles =
+            serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))?;
+
+        Ok(linked.primary().map(|p| Profile {
+            account_platform: p.membership_type,
+            account_id: p.membership_id.clone(),
+        }))
+    }
+
+    /// The Destiny membership of the signed-in user that holds their history
+    pub async fn get_current_user_profile(access_token: &str) -> Result<Option<Profile>, ApiError> {
+        let res_val =
+            make_authorized_request(BungieRequest::GetMembershipsForCurrentUser, access_token)
+                .await
+                .map_err(|e| ApiError::ResponseError(e))?;
+
+        let memberships: UserMemberships =
+            serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))?;
+
+        Ok(memberships.primary().map(|m| Profile {
+            account_platform: m.membership_type,
+            account_id: m.membership_id.clone(),
+        }))
+    }
+
     pub async fn get_profile_activities(
         profile: &Profile,
     ) -> Result<ProfileCurrentActivities, ApiError> {
         let res_val = make_request(BungieRequest::GetProfile {
             membership_type: profile.account_platform,
             membership_id: &profile.account_id,
-            component: 204,
+            // Character activities, progressions for encounter phases, plus transitory data for the fireteam
+            components: &[202, 204, 1000],
+        })
+        .await
+        .map_err(|e| ApiError::ResponseError(e))?;
+
+        serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
+    }
+
+    pub async fn get_characters(profile: &Profile) -> Result<ProfileCharacters, ApiError> {
+        let res_val = make_request(BungieRequest::GetProfile {
+            membership_type: profile.account_platform,
+            membership_id: &profile.account_id,
+            components: &[200],
         })
         .await
         .map_err(|e| ApiError::ResponseError(e))?;
@@ -180,4 +483,16 @@ impl Api {
 
         serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
     }
+
+    pub async fn get_career_stats(profile: &Profile) -> Result<CareerStats, ApiError> {
+        let res_val = make_request(BungieRequest::GetHistoricalStats {
+            membership_type: profile.account_platform,
+            membership_id: &profile.account_id,
+            modes: &[RAID_ACTIVITY_MODE, DUNGEON_ACTIVITY_MODE],
+        })
+        .await
+        .map_err(|e| ApiError::ResponseError(e))?;
+
+        serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
+    }
 }
@@ -0,0 +1,274 @@
+use std::{collections::HashMap, net::Ipv4Addr, sync::LazyLock};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::{RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+use tauri::{api::shell, AppHandle, Manager};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+};
+use tracing::{info, warn};
+
+use super::requests::HTTP_CLIENT;
+use crate::{
+    config::auth::TokenStore,
+    consts::{
+        get_oauth_client_id, get_oauth_client_secret, LOCAL_SERVER_MAX_REQUEST_BYTES,
+        OAUTH_AUTHORIZE_URL, OAUTH_CALLBACK_TIMEOUT, OAUTH_REDIRECT_PATH, OAUTH_REDIRECT_PORT,
+        OAUTH_REFRESH_MARGIN_SECS, OAUTH_STATE_LENGTH, OAUTH_TOKEN_URL, USER_AGENT,
+    },
+};
+
+// Held across refreshes so concurrent requests don't each spend the same refresh token
+static SESSION: LazyLock<Mutex<Option<AuthToken>>> =
+    LazyLock::new(|| Mutex::new(TokenStore::read().ok().and_then(|s| s.token)));
+
+/// An OAuth access token for the signed-in Bungie.net user
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthToken {
+    pub access_token: String,
+    pub expires_at: DateTime<Utc>,
+    /// The Bungie.net membership, not a Destiny one
+    pub membership_id: String,
+    /// Only issued to confidential clients
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub refresh_expires_at: Option<DateTime<Utc>>,
+}
+
+impl AuthToken {
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at - Duration::seconds(OAUTH_REFRESH_MARGIN_SECS) > now
+    }
+
+    fn can_refresh(&self, now: DateTime<Utc>) -> bool {
+        self.refresh_token.is_some() && self.refresh_expires_at.map_or(false, |e| e > now)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+    membership_id: String,
+    refresh_token: Option<String>,
+    refresh_expires_in: Option<i64>,
+}
+
+/// A usable access token for the signed-in user, refreshed first if it's about to expire.
+/// `None` when signed out, or when the session can't be renewed without signing in again.
+pub async fn access_token() -> Option<String> {
+    let mut session = SESSION.lock().await;
+    let token = session.as_ref()?;
+    let now = Utc::now();
+
+    if token.is_fresh(now) {
+        return Some(token.access_token.clone());
+    }
+
+    // Public clients never get a refresh token, so their session is over rather than left
+    // looking signed in with a token that no longer works
+    if !token.can_refresh(now) {
+        info!(target: "auth", "🔑 Auth: Session expired, sign in again to renew it");
+        *session = None;
+        persist(&session);
+
+        return None;
+    }
+
+    match refresh(token).await {
+        Ok(token) => {
+            let access_token = token.access_token.clone();
+            *session = Some(token);
+            persist(&session);
+
+            Some(access_token)
+        }
+        Err(e) => {
+            warn!(target: "auth", "🔑 Auth: Failed to refresh token: {}", e);
+
+            None
+        }
+    }
+}
+
+pub async fn is_signed_in() -> bool {
+    SESSION.lock().await.is_some()
+}
+
+pub async fn sign_out() {
+    let mut session = SESSION.lock().await;
+    *session = None;
+    persist(&session);
+}
+
+fn persist(session: &Option<AuthToken>) {
+    let store = TokenStore {
+        token: session.clone(),
+    };
+
+    if let Err(e) = store.save() {
+        warn!(target: "auth", "🔑 Auth: Failed to save session: {}", e);
+    }
+}
+
+/// Opens the Bungie sign-in page in the browser and waits for it to redirect back to us
+pub async fn sign_in(handle: &AppHandle) -> Result<AuthToken> {
+    let client_id =
+        get_oauth_client_id().ok_or_else(|| anyhow!("Sign-in isn't available in this build"))?;
+
+    // Bind before opening the browser so the redirect can't arrive first
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, OAUTH_REDIRECT_PORT)).await?;
+
+    let state: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(OAUTH_STATE_LENGTH)
+        .map(char::from)
+        .collect();
+
+    let url = Url::parse_with_params(
+        OAUTH_AUTHORIZE_URL,
+        &[
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("state", state.as_str()),
+        ],
+    )?;
+
+    shell::open(&handle.shell_scope(), url.as_str(), None)?;
+
+    let code = tokio::time::timeout(OAUTH_CALLBACK_TIMEOUT, wait_for_code(&listener, &state))
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for sign-in"))??;
+
+    let token = request_token(&client_id, &[("grant_type", "authorization_code"), ("code", code.as_str())]).await?;
+
+    let mut session = SESSION.lock().await;
+    *session = Some(token.clone());
+    persist(&session);
+
+    Ok(token)
+}
+
+async fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+
+        let path = match read_request_path(&mut stream).await {
+            Some(p) => p,
+            None => continue,
+        };
+
+        let url = match Url::parse(&format!("http://127.0.0.1{path}")) {
+            Ok(u) => u,
+            Err(_) => continue,
+        };
+
+        // Browsers also ask for things like /favicon.ico
+        if url.path() != OAUTH_REDIRECT_PATH {
+            let _ = respond(&mut stream, "404 Not Found", "").await;
+            continue;
+        }
+
+        let params: HashMap<_, _> = url.query_pairs().collect();
+
+        if params.get("state").map_or(true, |s| *s != state) {
+            let _ = respond(&mut stream, "400 Bad Request", "Sign-in failed, try again.").await;
+            return Err(anyhow!("Sign-in failed, the response didn't match the request"));
+        }
+
+        return match params.get("code") {
+            Some(code) => {
+                let _ = respond(&mut stream, "200 OK", "Signed in, you can close this tab.").await;
+                Ok(code.to_string())
+            }
+            None => {
+                let _ = respond(&mut stream, "400 Bad Request", "Sign-in was cancelled.").await;
+                Err(anyhow!("Sign-in was cancelled"))
+            }
+        };
+    }
+}
+
+async fn read_request_path(stream: &mut TcpStream) -> Option<String> {
+    let mut buf = vec![0; LOCAL_SERVER_MAX_REQUEST_BYTES];
+    let mut len = 0;
+
+    // The path is all we need, so stop at the end of the request line
+    while !buf[..len].windows(2).any(|w| w == b"\r\n") {
+        if len == buf.len() {
+            return None;
+        }
+
+        match stream.read(&mut buf[len..]).await.ok()? {
+            0 => return None,
+            n => len += n,
+        }
+    }
+
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let path = request.lines().next()?.split_whitespace().nth(1)?;
+
+    Some(path.to_string())
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n\
+         {body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn refresh(token: &AuthToken) -> Result<AuthToken> {
+    let client_id = get_oauth_client_id().ok_or_else(|| anyhow!("Sign-in isn't available in this build"))?;
+    let refresh_token = token.refresh_token.as_deref().ok_or_else(|| anyhow!("No refresh token"))?;
+
+    request_token(&client_id, &[("grant_type", "refresh_token"), ("refresh_token", refresh_token)]).await
+}
+
+fn token_request(client_id: &str, form: &[(&str, &str)]) -> RequestBuilder {
+    let builder = HTTP_CLIENT.post(OAUTH_TOKEN_URL).header("User-Agent", USER_AGENT);
+
+    // Confidential clients authenticate with their secret, public ones just name themselves
+    match get_oauth_client_secret() {
+        Some(secret) => builder.basic_auth(client_id, Some(secret)).form(form),
+        None => builder.form(&[form, &[("client_id", client_id)]].concat()),
+    }
+}
+
+async fn request_token(client_id: &str, form: &[(&str, &str)]) -> Result<AuthToken> {
+    let resp = token_request(client_id, form).send().await?;
+
+    let status = resp.status();
+    let text = resp.text().await?;
+
+    if !status.is_success() {
+        return Err(anyhow!("Sign-in failed ({status})"));
+    }
+
+    let token: TokenResponse = serde_json::from_str(&text)?;
+    let now = Utc::now();
+
+    info!(target: "auth", "🔑 Auth: Got token for Bungie.net member {}", token.membership_id);
+
+    Ok(AuthToken {
+        access_token: token.access_token,
+        expires_at: now + Duration::seconds(token.expires_in),
+        membership_id: token.membership_id,
+        refresh_token: token.refresh_token,
+        refresh_expires_at: token.refresh_expires_in.map(|s| now + Duration::seconds(s)),
+    })
+}
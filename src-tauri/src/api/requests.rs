@@ -1,26 +1,104 @@
 use std::{
     error::Error,
     fmt::{Display, Formatter},
-    sync::LazyLock,
+    sync::{LazyLock, Mutex},
+    time::Duration,
 };
 
+use rand::Rng;
 use reqwest::{Client, Method, RequestBuilder};
 use serde::Deserialize;
 use serde_json::{json, Value};
+use tokio::time::Instant;
 
-use crate::consts::{get_api_key, API_PATH, USER_AGENT};
+use super::auth;
+use crate::consts::{
+    get_api_key, ACTIVITY_HISTORY_PAGE_SIZE, API_PATH, API_RATE_LIMIT_PER_SEC,
+    HTTP_REQUEST_TIMEOUT, MAX_THROTTLE_WAIT_SECS, RETRY_JITTER_RANGE, THROTTLE_BUFFER_MILLIS, USER_AGENT,
+};
+
+pub(super) static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    Client::builder()
+        .timeout(HTTP_REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+});
+
+static RATE_LIMITER: LazyLock<TokenBucket> =
+    LazyLock::new(|| TokenBucket::new(API_RATE_LIMIT_PER_SEC as f64));
+
+/// Applies a changed `requests_per_second` preference to every request from now on
+pub fn set_rate_limit(requests_per_second: u32) {
+    RATE_LIMITER.set_rate(requests_per_second.max(1) as f64);
+}
+
+/// Bounds the rate of outgoing requests, independent of how many are in flight. Holds up to a
+/// second's worth of tokens, so bursts stay within the rate too.
+struct TokenBucket {
+    state: Mutex<TokenBucketState>,
+}
+
+struct TokenBucketState {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        Self {
+            state: Mutex::new(TokenBucketState {
+                rate,
+                tokens: rate,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    fn set_rate(&self, rate: f64) {
+        let mut state = self.state.lock().unwrap();
+
+        state.rate = rate;
+        state.tokens = state.tokens.min(rate);
+    }
+
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * state.rate).min(state.rate);
+                state.last_refill = now;
+
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+
+                Duration::from_secs_f64((1.0 - state.tokens) / state.rate)
+            };
 
-static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
 
 pub enum BungieRequest<'a> {
     SearchDestinyPlayerByBungieName {
         display_name: &'a str,
         display_name_code: usize,
     },
+    /// Searches by platform display name (the old Steam/Xbox/PSN handle). -1 searches every platform.
+    SearchDestinyPlayer {
+        membership_type: isize,
+        display_name: &'a str,
+    },
     GetProfile {
         membership_type: usize,
         membership_id: &'a str,
-        component: usize,
+        components: &'a [usize],
     },
     GetActivityHistory {
         membership_type: usize,
@@ -35,6 +113,34 @@ pub enum BungieRequest<'a> {
     GetDestinyActivityDefinition {
         activity_hash: usize,
     },
+    GetDestinyDestinationDefinition {
+        destination_hash: usize,
+    },
+    GetDestinyPlaceDefinition {
+        place_hash: usize,
+    },
+    GetDestinyManifest,
+    GetLinkedProfiles {
+        membership_type: usize,
+        membership_id: &'a str,
+    },
+    GetHistoricalStats {
+        membership_type: usize,
+        membership_id: &'a str,
+        modes: &'a [usize],
+    },
+    /// Requires an OAuth access token, see `make_authorized_request`
+    GetMembershipsForCurrentUser,
+}
+
+impl BungieRequest<'_> {
+    /// Requests whose data a private profile only shows to its signed-in owner
+    fn reads_private_data(&self) -> bool {
+        matches!(
+            self,
+            BungieRequest::GetProfile { .. } | BungieRequest::GetActivityHistory { .. }
+        )
+    }
 }
 
 #[derive(Deserialize)]
@@ -58,9 +164,35 @@ pub enum BungieResponseError {
         throttle_seconds: isize,
     },
     ResponseMissing,
+    ServiceUnavailable {
+        retries: u32,
+    },
     NetworkError(anyhow::Error),
 }
 
+impl BungieResponseError {
+    /// Whether Bungie is asking us to slow down, as opposed to the request itself failing
+    pub fn is_throttling(&self) -> bool {
+        match self {
+            BungieResponseError::ServiceUnavailable { .. } => true,
+            BungieResponseError::BungieError {
+                throttle_seconds, ..
+            } => *throttle_seconds > 0,
+            _ => false,
+        }
+    }
+
+    /// Whether the same request may succeed if sent again, e.g. a dropped connection or an
+    /// outage page, as opposed to Bungie rejecting it
+    pub fn is_transient(&self) -> bool {
+        match self {
+            BungieResponseError::NetworkError(_) => true,
+            BungieResponseError::DeserializeError { status_code, .. } => *status_code >= 500,
+            _ => self.is_throttling(),
+        }
+    }
+}
+
 impl Display for BungieResponseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -82,6 +214,9 @@ impl Display for BungieResponseError {
                 }
             }
             BungieResponseError::ResponseMissing => f.write_str("Response object missing"),
+            BungieResponseError::ServiceUnavailable { retries } => {
+                write!(f, "Bungie API unavailable (503) after {retries} retries")
+            }
             BungieResponseError::NetworkError(e) => e.fmt(f),
         }
     }
@@ -89,6 +224,17 @@ impl Display for BungieResponseError {
 
 impl Error for BungieResponseError {}
 
+/// Percent-encodes everything but unreserved characters, since handles can contain spaces and more
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
 fn api_request(path: &str, method: Method) -> RequestBuilder {
     HTTP_CLIENT
         .request(method, format!("{API_PATH}{path}"))
@@ -97,10 +243,28 @@ fn api_request(path: &str, method: Method) -> RequestBuilder {
 }
 
 pub async fn make_request(req: BungieRequest<'_>) -> Result<Value, BungieResponseError> {
-    make_request_with_retry(req, 3).await
+    let access_token = if req.reads_private_data() {
+        auth::access_token().await
+    } else {
+        None
+    };
+
+    make_request_with_retry(req, 3, access_token.as_deref()).await
+}
+
+/// Same as `make_request`, but on behalf of the signed-in user
+pub async fn make_authorized_request(
+    req: BungieRequest<'_>,
+    access_token: &str,
+) -> Result<Value, BungieResponseError> {
+    make_request_with_retry(req, 3, Some(access_token)).await
 }
 
-async fn make_request_with_retry(req: BungieRequest<'_>, max_retries: u32) -> Result<Value, BungieResponseError> {
+async fn make_request_with_retry(
+    req: BungieRequest<'_>,
+    max_retries: u32,
+    access_token: Option<&str>,
+) -> Result<Value, BungieResponseError> {
     let mut retry_count = 0;
     
     loop {
@@ -109,22 +273,53 @@ async fn make_request_with_retry(req: BungieRequest<'_>, max_retries: u32) -> Re
                 "/Destiny2/SearchDestinyPlayerByBungieName/All",
                 Method::POST,
             ).body(json!({"displayName": display_name, "displayNameCode": display_name_code}).to_string()),
-            BungieRequest::GetProfile { membership_type, membership_id, component } => {
-                api_request(&format!("/Destiny2/{membership_type}/Profile/{membership_id}?components={component}"), Method::GET)
+            BungieRequest::SearchDestinyPlayer { membership_type, display_name } => api_request(
+                &format!("/Destiny2/SearchDestinyPlayer/{membership_type}/{}/", encode_path_segment(display_name)),
+                Method::GET,
+            ),
+            BungieRequest::GetProfile { membership_type, membership_id, components } => {
+                let components = components.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+                api_request(&format!("/Destiny2/{membership_type}/Profile/{membership_id}?components={components}"), Method::GET)
             }
             BungieRequest::GetActivityHistory { membership_type, membership_id, character_id, page, mode } => {
-                api_request(&format!("/Destiny2/{membership_type}/Account/{membership_id}/Character/{character_id}/Stats/Activities?mode={mode}&count=25&page={page}"), Method::GET)
+                api_request(&format!("/Destiny2/{membership_type}/Account/{membership_id}/Character/{character_id}/Stats/Activities?mode={mode}&count={ACTIVITY_HISTORY_PAGE_SIZE}&page={page}"), Method::GET)
             }
             BungieRequest::GetPostGameCarnageReport { activity_id } => {
                 api_request(&format!("/Destiny2/Stats/PostGameCarnageReport/{activity_id}"), Method::GET)
             }
             BungieRequest::GetDestinyActivityDefinition { activity_hash } => api_request(&format!("/Destiny2/Manifest/DestinyActivityDefinition/{activity_hash}"), Method::GET),
+            BungieRequest::GetDestinyDestinationDefinition { destination_hash } => api_request(&format!("/Destiny2/Manifest/DestinyDestinationDefinition/{destination_hash}"), Method::GET),
+            BungieRequest::GetDestinyPlaceDefinition { place_hash } => api_request(&format!("/Destiny2/Manifest/DestinyPlaceDefinition/{place_hash}"), Method::GET),
+            BungieRequest::GetDestinyManifest => api_request("/Destiny2/Manifest/", Method::GET),
+            BungieRequest::GetLinkedProfiles { membership_type, membership_id } => {
+                api_request(&format!("/Destiny2/{membership_type}/Profile/{membership_id}/LinkedProfiles/"), Method::GET)
+            }
+            // Character 0 merges every character, including deleted ones
+            BungieRequest::GetHistoricalStats { membership_type, membership_id, modes } => {
+                let modes = modes.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(",");
+                api_request(&format!("/Destiny2/{membership_type}/Account/{membership_id}/Character/0/Stats/?periodType=AllTime&modes={modes}"), Method::GET)
+            }
+            BungieRequest::GetMembershipsForCurrentUser => {
+                api_request("/User/GetMembershipsForCurrentUser/", Method::GET)
+            }
         };
 
-        let resp = builder
-            .send()
-            .await
-            .map_err(|e| BungieResponseError::NetworkError(e.into()))?;
+        let builder = match access_token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        };
+
+        RATE_LIMITER.acquire().await;
+
+        let resp = match builder.send().await {
+            Ok(r) => r,
+            // A hung connection is dropped by the client timeout, so just try again
+            Err(e) if e.is_timeout() && retry_count < max_retries => {
+                retry_count += 1;
+                continue;
+            }
+            Err(e) => return Err(BungieResponseError::NetworkError(e.into())),
+        };
 
         let status_code = resp.status().as_u16();
         
@@ -132,20 +327,26 @@ async fn make_request_with_retry(req: BungieRequest<'_>, max_retries: u32) -> Re
         if status_code == 503 {
             if retry_count < max_retries {
                 retry_count += 1;
-                let wait_time = 2u64.pow(retry_count); // Exponential backoff: 2s, 4s, 8s
-                tokio::time::sleep(tokio::time::Duration::from_secs(wait_time)).await;
+                // Exponential backoff (2s, 4s, 8s) with jitter
+                let backoff = 2u64.pow(retry_count) as f64;
+                let jitter = rand::thread_rng().gen_range(RETRY_JITTER_RANGE);
+                tokio::time::sleep(tokio::time::Duration::from_secs_f64(backoff * jitter)).await;
                 continue;
             } else {
-                return Err(BungieResponseError::NetworkError(
-                    anyhow::anyhow!("Bungie API unavailable (503) after {} retries", max_retries)
-                ));
+                return Err(BungieResponseError::ServiceUnavailable {
+                    retries: max_retries,
+                });
             }
         }
 
-        let text = resp
-            .text()
-            .await
-            .map_err(|e| BungieResponseError::NetworkError(e.into()))?;
+        let text = match resp.text().await {
+            Ok(t) => t,
+            Err(e) if e.is_timeout() && retry_count < max_retries => {
+                retry_count += 1;
+                continue;
+            }
+            Err(e) => return Err(BungieResponseError::NetworkError(e.into())),
+        };
 
         let status: BungieResponseStatus = match serde_json::from_str(&text) {
             Ok(s) => s,
@@ -159,6 +360,18 @@ async fn make_request_with_retry(req: BungieRequest<'_>, max_retries: u32) -> Re
         };
 
         if status.error_code != 1 {
+            // Throttled by Bungie, wait out the requested period and retry
+            if status.throttle_seconds > 0 && retry_count < max_retries {
+                retry_count += 1;
+                let wait_time = (status.throttle_seconds as u64).min(MAX_THROTTLE_WAIT_SECS);
+                tokio::time::sleep(
+                    tokio::time::Duration::from_secs(wait_time)
+                        + tokio::time::Duration::from_millis(THROTTLE_BUFFER_MILLIS),
+                )
+                .await;
+                continue;
+            }
+
             return Err(BungieResponseError::BungieError {
                 message: status.message,
                 error_code: status.error_code,
@@ -1,20 +1,86 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::consts::{RAID_ACTIVITY_HASH, RAID_ACTIVITY_MODE};
+use crate::consts::{
+    DUNGEON_ACTIVITY_HASH, DUNGEON_ACTIVITY_MODE, PGCR_VERSION, RAID_ACTIVITY_HASH,
+    RAID_ACTIVITY_MODE,
+};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BungieProfile {
     membership_type: usize,
     membership_id: String,
+    // Legacy platform name searches can turn up accounts that never claimed a Bungie name
+    #[serde(default)]
     bungie_global_display_name: String,
+    #[serde(default)]
     bungie_global_display_name_code: usize,
     cross_save_override: usize,
 }
 
+impl BungieProfile {
+    /// Search returns every platform membership; with cross-save only one of them owns the data
+    pub fn is_cross_save_primary(&self) -> bool {
+        self.cross_save_override == 0 || self.cross_save_override == self.membership_type
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedProfiles {
+    pub profiles: Vec<LinkedMembership>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedMembership {
+    pub membership_type: usize,
+    pub membership_id: String,
+    #[serde(default)]
+    pub is_cross_save_primary: bool,
+}
+
+impl LinkedProfiles {
+    pub fn primary(&self) -> Option<&LinkedMembership> {
+        self.profiles.iter().find(|p| p.is_cross_save_primary)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserMemberships {
+    pub destiny_memberships: Vec<UserMembership>,
+    /// Only set when the account has cross-save enabled
+    pub primary_membership_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserMembership {
+    pub membership_type: usize,
+    pub membership_id: String,
+    #[serde(default)]
+    pub cross_save_override: usize,
+}
+
+impl UserMemberships {
+    pub fn primary(&self) -> Option<&UserMembership> {
+        if let Some(id) = &self.primary_membership_id {
+            return self.destiny_memberships.iter().find(|m| &m.membership_id == id);
+        }
+
+        self.destiny_memberships
+            .iter()
+            .find(|m| m.cross_save_override == 0 || m.cross_save_override == m.membership_type)
+    }
+}
+
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ProfileInfo {
@@ -70,9 +136,120 @@ impl<'de> Deserialize<'de> for ProfileInfo {
     }
 }
 
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CharacterClass {
+    Titan,
+    Hunter,
+    Warlock,
+    Unknown,
+}
+
+impl CharacterClass {
+    /// Reports carry the class definition hash rather than the class type
+    fn from_class_hash(class_hash: usize) -> Self {
+        match class_hash {
+            3655393761 => CharacterClass::Titan,
+            671679327 => CharacterClass::Hunter,
+            2271682572 => CharacterClass::Warlock,
+            _ => CharacterClass::Unknown,
+        }
+    }
+}
+
+impl From<usize> for CharacterClass {
+    fn from(class_type: usize) -> Self {
+        match class_type {
+            0 => CharacterClass::Titan,
+            1 => CharacterClass::Hunter,
+            2 => CharacterClass::Warlock,
+            _ => CharacterClass::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Character {
+    pub character_id: String,
+    pub class: CharacterClass,
+    pub light: usize,
+    /// Relative to bungie.net, like the other manifest images
+    pub emblem_path: Option<String>,
+    pub emblem_background_path: Option<String>,
+    pub date_last_played: DateTime<Utc>,
+}
+
+/// Every character on a profile, most recently played first
+#[derive(Debug)]
+pub struct ProfileCharacters(pub Vec<Character>);
+
+impl<'de> Deserialize<'de> for ProfileCharacters {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Profile {
+            characters: _Characters,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Characters {
+            #[serde(default)]
+            data: HashMap<String, _Character>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Character {
+            class_type: usize,
+            light: usize,
+            #[serde(default)]
+            emblem_path: Option<String>,
+            #[serde(default)]
+            emblem_background_path: Option<String>,
+            date_last_played: DateTime<Utc>,
+        }
+
+        let profile = _Profile::deserialize(deserializer)?;
+
+        let mut characters: Vec<Character> = profile
+            .characters
+            .data
+            .into_iter()
+            .map(|(character_id, c)| Character {
+                character_id,
+                class: c.class_type.into(),
+                light: c.light,
+                emblem_path: c.emblem_path,
+                emblem_background_path: c.emblem_background_path,
+                date_last_played: c.date_last_played,
+            })
+            .collect();
+
+        characters.sort_by(|a, b| b.date_last_played.cmp(&a.date_last_played));
+
+        Ok(Self(characters))
+    }
+}
+
 #[derive(Debug)]
 pub struct ProfileCurrentActivities {
     pub activities: Option<HashMap<String, LatestCharacterActivity>>,
+    /// Empty when the player is offline or hides their transitory data
+    pub fireteam: Vec<FireteamMember>,
+    /// Transitory data only exists while the player is in game
+    pub online: bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FireteamMember {
+    pub membership_id: String,
+    pub display_name: String,
 }
 
 #[derive(Debug, Serialize, Clone, PartialEq, Eq)]
@@ -80,6 +257,18 @@ pub struct ProfileCurrentActivities {
 pub struct LatestCharacterActivity {
     pub date_activity_started: DateTime<Utc>,
     pub current_activity_hash: usize,
+    pub current_activity_mode_type: Option<usize>,
+    /// Only known for activities with a weekly milestone that tracks encounters, e.g. raids
+    pub phase: Option<ActivityPhase>,
+}
+
+/// Encounter progress, from the milestone phases completed this week
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityPhase {
+    /// 1-based, the encounter being played
+    pub current: usize,
+    pub total: usize,
 }
 
 impl PartialOrd for LatestCharacterActivity {
@@ -104,6 +293,65 @@ impl<'de> Deserialize<'de> for ProfileCurrentActivities {
         #[serde(rename_all = "camelCase")]
         struct _Profile {
             character_activities: _CurrentActivities,
+            #[serde(default)]
+            profile_transitory_data: Option<_Transitory>,
+            #[serde(default)]
+            character_progressions: Option<_Progressions>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Progressions {
+            data: Option<HashMap<String, _CharacterProgression>>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _CharacterProgression {
+            #[serde(default)]
+            milestones: HashMap<String, _Milestone>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Milestone {
+            #[serde(default)]
+            activities: Vec<_MilestoneActivity>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _MilestoneActivity {
+            activity_hash: usize,
+            #[serde(default)]
+            phases: Vec<_Phase>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Phase {
+            complete: bool,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Transitory {
+            data: Option<_TransitoryData>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _TransitoryData {
+            #[serde(default)]
+            party_members: Vec<_PartyMember>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _PartyMember {
+            membership_id: String,
+            #[serde(default)]
+            display_name: String,
         }
 
         #[derive(Deserialize)]
@@ -119,23 +367,65 @@ impl<'de> Deserialize<'de> for ProfileCurrentActivities {
         struct _CurrentActivity {
             date_activity_started: DateTime<Utc>,
             current_activity_hash: usize,
+            #[serde(default)]
+            current_activity_mode_type: Option<usize>,
         }
 
         let profile = _Profile::deserialize(deserializer)?;
+        let transitory = profile.profile_transitory_data.and_then(|t| t.data);
+        let mut progressions = profile
+            .character_progressions
+            .and_then(|p| p.data)
+            .unwrap_or_default();
+
+        // The encounter after the last completed one, capped at the final encounter once it's done
+        let mut phase = |character_id: &str, activity_hash: usize| {
+            progressions
+                .remove(character_id)?
+                .milestones
+                .into_values()
+                .flat_map(|m| m.activities)
+                .find(|a| a.activity_hash == activity_hash && !a.phases.is_empty())
+                .map(|a| {
+                    let completed = a.phases.iter().filter(|p| p.complete).count();
+
+                    ActivityPhase {
+                        current: (completed + 1).min(a.phases.len()),
+                        total: a.phases.len(),
+                    }
+                })
+        };
+
         Ok(Self {
+            online: transitory.is_some(),
             activities: profile.character_activities.data.map(|d| {
                 d.into_iter()
                     .map(|e| {
+                        let phase = phase(&e.0, e.1.current_activity_hash);
+
                         (
                             e.0,
                             LatestCharacterActivity {
                                 date_activity_started: e.1.date_activity_started,
                                 current_activity_hash: e.1.current_activity_hash,
+                                current_activity_mode_type: e.1.current_activity_mode_type,
+                                phase,
                             },
                         )
                     })
                     .collect()
             }),
+            fireteam: transitory
+                .map(|d| {
+                    d.party_members
+                        .into_iter()
+                        .map(|m| FireteamMember {
+                            membership_id: m.membership_id,
+                            display_name: m.display_name,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
         })
     }
 }
@@ -170,6 +460,50 @@ pub struct CompletedActivity {
     pub starting_phase_index: Option<usize>,
     #[serde(default)]
     pub activity_was_started_from_beginning: Option<bool>,
+    #[serde(default)]
+    pub fireteam_size: Option<u8>,
+    #[serde(default)]
+    pub kills: Option<u32>,
+    #[serde(default)]
+    pub deaths: Option<u32>,
+    #[serde(default)]
+    pub assists: Option<u32>,
+    #[serde(default)]
+    pub flawless: bool,
+    /// `PGCR_VERSION` when the PGCR was last applied, 0 if it never has been
+    #[serde(default)]
+    pub pgcr_version: u32,
+}
+
+impl CompletedActivity {
+    /// Whether every field filled in from the PGCR is present. Not every report carries
+    /// `activityWasStartedFromBeginning`, so that can't stand in for it.
+    pub fn pgcr_fetched(&self) -> bool {
+        self.pgcr_version >= PGCR_VERSION
+    }
+
+    /// `None` until the PGCR has been fetched. Falls back to the starting phase for reports
+    /// that don't carry `activityWasStartedFromBeginning`.
+    pub fn is_fresh_start(&self) -> Option<bool> {
+        self.activity_was_started_from_beginning
+            .or(self.starting_phase_index.map(|i| i == 0))
+    }
+
+    /// Whether this was a fresh run, treating runs without a PGCR yet as not fresh
+    pub fn is_fresh(&self) -> bool {
+        self.is_fresh_start() == Some(true)
+    }
+
+    pub fn apply_pgcr(&mut self, pgcr: &PostGameCarnageReport) {
+        self.starting_phase_index = pgcr.starting_phase_index;
+        self.activity_was_started_from_beginning = pgcr.activity_was_started_from_beginning;
+        self.fireteam_size = pgcr.fireteam_size;
+        self.kills = pgcr.kills;
+        self.deaths = pgcr.deaths;
+        self.assists = pgcr.assists;
+        self.flawless = self.completed && self.deaths == Some(0) && self.is_fresh();
+        self.pgcr_version = PGCR_VERSION;
+    }
 }
 
 impl PartialOrd for CompletedActivity {
@@ -237,38 +571,91 @@ impl From<ApiCompletedActivity> for CompletedActivity {
             completion_reason,
             starting_phase_index: None, // Will be populated from PGCR
             activity_was_started_from_beginning: None, // Will be populated from PGCR
+            fireteam_size: None, // Will be populated from PGCR
+            kills: None, // Will be populated from PGCR
+            deaths: None, // Will be populated from PGCR
+            assists: None, // Will be populated from PGCR
+            flawless: false,
+            pgcr_version: 0,
         }
     }
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ActivityInfo {
     pub name: String,
     pub activity_modes: Vec<usize>,
     pub background_image: Option<String>,
+    /// e.g. "The Dreaming City", missing for definitions stored before it was fetched
+    #[serde(default)]
+    pub destination: Option<String>,
+    /// The planet or area the destination is on, e.g. "The Reef"
+    #[serde(default)]
+    pub place: Option<String>,
 }
 
-impl<'de> Deserialize<'de> for ActivityInfo {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        #[derive(Deserialize)]
-        #[serde(rename_all = "camelCase")]
-        struct _Activity {
-            original_display_properties: _DisplayProperties,
-            activity_mode_types: Option<Vec<usize>>,
-            activity_type_hash: usize,
-            pgcr_image: Option<String>,
-        }
+/// A raid or dungeon the specific filters can target
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackableActivity {
+    pub name: String,
+    pub is_raid: bool,
+    /// Every definition sharing the name, e.g. normal and master versions
+    pub hashes: Vec<usize>,
+}
 
-        #[derive(Deserialize)]
-        #[serde(rename_all = "camelCase")]
-        struct _DisplayProperties {
-            name: String,
-        }
+impl ActivityInfo {
+    pub fn is_raid(&self) -> bool {
+        self.activity_modes.contains(&RAID_ACTIVITY_MODE)
+    }
+
+    pub fn is_dungeon(&self) -> bool {
+        self.activity_modes.contains(&DUNGEON_ACTIVITY_MODE)
+    }
 
+    /// Shared by reissues of the same activity under new hashes, e.g. "Vault of Glass" and
+    /// "Vault of Glass: Master" or "Vault of Glass (Legend)"
+    pub fn family_key(&self) -> String {
+        let name = self.name.split([':', '(']).next().unwrap_or_default();
+        name.trim().to_lowercase()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ApiActivityDefinition {
+    original_display_properties: ApiDisplayProperties,
+    activity_mode_types: Option<Vec<usize>>,
+    activity_type_hash: usize,
+    pgcr_image: Option<String>,
+    #[serde(default)]
+    pub(crate) destination_hash: usize,
+    #[serde(default)]
+    pub(crate) place_hash: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiDisplayProperties {
+    name: String,
+}
+
+/// Any definition where only the display name is needed, e.g. destinations and places
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ApiNamedDefinition {
+    display_properties: ApiDisplayProperties,
+}
+
+impl ApiNamedDefinition {
+    pub(crate) fn into_name(self) -> Option<String> {
+        Some(self.display_properties.name).filter(|n| !n.is_empty())
+    }
+}
+
+impl From<ApiActivityDefinition> for ActivityInfo {
+    fn from(definition: ApiActivityDefinition) -> Self {
         fn modes_from_hash(hash: usize) -> Vec<usize> {
             let mut v = vec![];
 
@@ -276,23 +663,64 @@ impl<'de> Deserialize<'de> for ActivityInfo {
                 v.push(RAID_ACTIVITY_MODE);
             }
 
+            if hash == DUNGEON_ACTIVITY_HASH {
+                v.push(DUNGEON_ACTIVITY_MODE);
+            }
+
             v
         }
 
-        let activity = _Activity::deserialize(deserializer)?;
-        Ok(Self {
-            name: activity.original_display_properties.name,
-            activity_modes: activity
+        Self {
+            name: definition.original_display_properties.name,
+            activity_modes: definition
                 .activity_mode_types
-                .unwrap_or_else(|| modes_from_hash(activity.activity_type_hash)),
-            background_image: activity.pgcr_image,
-        })
+                .unwrap_or_else(|| modes_from_hash(definition.activity_type_hash)),
+            background_image: definition.pgcr_image,
+            destination: None,
+            place: None,
+        }
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DestinyManifest {
+    pub version: String,
+}
+
+/// A single activity's post game carnage report. The totals feed the cached history, the rest
+/// backs the clear details view.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct PostGameCarnageReport {
+    pub instance_id: String,
+    pub activity_hash: usize,
+    pub period: DateTime<Utc>,
+    pub completed: bool,
+    pub activity_duration_seconds: usize,
     pub activity_was_started_from_beginning: Option<bool>,
     pub starting_phase_index: Option<usize>,
+    pub fireteam_size: Option<u8>,
+    pub kills: Option<u32>,
+    pub deaths: Option<u32>,
+    pub assists: Option<u32>,
+    pub players: Vec<PgcrPlayer>,
+}
+
+/// One character's entry in a report
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PgcrPlayer {
+    pub membership_id: String,
+    pub character_id: String,
+    pub display_name: String,
+    pub class: CharacterClass,
+    pub light: usize,
+    pub completed: bool,
+    pub kills: u32,
+    pub deaths: u32,
+    pub assists: u32,
+    pub time_played_seconds: usize,
 }
 
 impl<'de> Deserialize<'de> for PostGameCarnageReport {
@@ -303,14 +731,197 @@ impl<'de> Deserialize<'de> for PostGameCarnageReport {
         #[derive(Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct _Report {
+            period: DateTime<Utc>,
+            activity_details: _ActivityDetails,
             activity_was_started_from_beginning: Option<bool>,
             starting_phase_index: Option<usize>,
+            #[serde(default)]
+            entries: Vec<_Entry>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _ActivityDetails {
+            instance_id: String,
+            director_activity_hash: usize,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Entry {
+            character_id: String,
+            player: _Player,
+            values: _EntryValues,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _EntryValues {
+            kills: Option<ApiValue>,
+            deaths: Option<ApiValue>,
+            assists: Option<ApiValue>,
+            completed: Option<ApiValue>,
+            completion_reason: Option<ApiValue>,
+            activity_duration_seconds: Option<ApiValue>,
+            time_played_seconds: Option<ApiValue>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Player {
+            destiny_user_info: _UserInfo,
+            #[serde(default)]
+            class_hash: usize,
+            #[serde(default)]
+            light_level: usize,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _UserInfo {
+            membership_id: String,
+            #[serde(default)]
+            display_name: String,
+            #[serde(default)]
+            bungie_global_display_name: String,
         }
 
         let report = _Report::deserialize(deserializer)?;
+
+        // Entries are per character, so a player who swapped characters mid-run appears twice
+        let players: HashSet<&str> = report
+            .entries
+            .iter()
+            .map(|e| e.player.destiny_user_info.membership_id.as_str())
+            .collect();
+
+        let fireteam_size = match players.len() {
+            0 => None,
+            n => Some(n.min(u8::MAX as usize) as u8),
+        };
+
+        // Fireteam totals across every entry
+        let total = |value: fn(&_EntryValues) -> Option<&ApiValue>| match report.entries.is_empty() {
+            true => None,
+            false => Some(
+                report
+                    .entries
+                    .iter()
+                    .filter_map(|e| value(&e.values))
+                    .map(|v| v.basic.value as u32)
+                    .sum(),
+            ),
+        };
+
+        let value = |v: &Option<ApiValue>| v.as_ref().map(|v| v.basic.value).unwrap_or(0.0);
+
+        let entry_completed =
+            |e: &_Entry| value(&e.values.completed) == 1.0 && value(&e.values.completion_reason) == 0.0;
+
+        let kills = total(|v| v.kills.as_ref());
+        let deaths = total(|v| v.deaths.as_ref());
+        let assists = total(|v| v.assists.as_ref());
+
+        let completed = report.entries.iter().any(|e| entry_completed(e));
+
+        // Every entry reports the same activity duration
+        let activity_duration_seconds = report
+            .entries
+            .iter()
+            .map(|e| value(&e.values.activity_duration_seconds) as usize)
+            .max()
+            .unwrap_or(0);
+
+        let players = report
+            .entries
+            .iter()
+            .map(|e| {
+                let user = &e.player.destiny_user_info;
+
+                PgcrPlayer {
+                    membership_id: user.membership_id.clone(),
+                    character_id: e.character_id.clone(),
+                    display_name: match user.bungie_global_display_name.is_empty() {
+                        true => user.display_name.clone(),
+                        false => user.bungie_global_display_name.clone(),
+                    },
+                    class: CharacterClass::from_class_hash(e.player.class_hash),
+                    light: e.player.light_level,
+                    completed: entry_completed(e),
+                    kills: value(&e.values.kills) as u32,
+                    deaths: value(&e.values.deaths) as u32,
+                    assists: value(&e.values.assists) as u32,
+                    time_played_seconds: value(&e.values.time_played_seconds) as usize,
+                }
+            })
+            .collect();
+
         Ok(Self {
+            instance_id: report.activity_details.instance_id,
+            activity_hash: report.activity_details.director_activity_hash,
+            period: report.period,
+            completed,
+            activity_duration_seconds,
             activity_was_started_from_beginning: report.activity_was_started_from_beginning,
             starting_phase_index: report.starting_phase_index,
+            fireteam_size,
+            kills,
+            deaths,
+            assists,
+            players,
+        })
+    }
+}
+
+/// Lifetime totals across all characters, independent of the cached history
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CareerStats {
+    pub raid_clears: u32,
+    pub dungeon_clears: u32,
+    pub fastest_raid_clear_seconds: Option<u32>,
+    pub fastest_dungeon_clear_seconds: Option<u32>,
+    pub kills: u32,
+    pub deaths: u32,
+}
+
+impl<'de> Deserialize<'de> for CareerStats {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _ModeStats {
+            // Absent when the player has never played the mode
+            all_time: Option<HashMap<String, ApiValue>>,
+        }
+
+        let modes = HashMap::<String, _ModeStats>::deserialize(deserializer)?;
+
+        let stat = |mode: &str, name: &str| {
+            modes
+                .get(mode)
+                .and_then(|m| m.all_time.as_ref())
+                .and_then(|s| s.get(name))
+                .map(|v| v.basic.value)
+        };
+
+        let count = |mode: &str, name: &str| stat(mode, name).unwrap_or(0.0) as u32;
+
+        let fastest = |mode: &str| {
+            stat(mode, "fastestCompletionMs")
+                .filter(|ms| *ms > 0.0)
+                .map(|ms| (ms / 1000.0) as u32)
+        };
+
+        Ok(Self {
+            raid_clears: count("raid", "activitiesCleared"),
+            dungeon_clears: count("dungeon", "activitiesCleared"),
+            fastest_raid_clear_seconds: fastest("raid"),
+            fastest_dungeon_clear_seconds: fastest("dungeon"),
+            kills: count("raid", "kills") + count("dungeon", "kills"),
+            deaths: count("raid", "deaths") + count("dungeon", "deaths"),
         })
     }
 }
@@ -1,13 +1,35 @@
-use std::{collections::HashMap, path::PathBuf};
-use anyhow::Result;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        LazyLock,
+    },
+};
+use anyhow::{bail, Result};
 use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use serde::{Deserialize, Serialize};
-use tokio::fs;
+use tokio::{fs, sync::Mutex};
+use tracing::{debug, error, info, warn};
 
-use crate::api::responses::CompletedActivity;
+use crate::{
+    api::responses::{ActivityInfo, CompletedActivity},
+    config::profiles::Profile,
+    consts::{MAX_CACHED_ACTIVITIES_PER_PROFILE, MAX_CACHED_PROFILES},
+};
 
 const CACHE_VERSION: u32 = 2; // Increment this to invalidate old caches
 
+static WRITE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Invariant: the activity cache file is only ever written while holding `SAVE_LOCK`, and never
+/// by an older snapshot than the one already on disk. Snapshots take their generation while the
+/// `CacheContainer` lock is held, so generations follow the order the cache was modified in.
+static SAVE_GENERATION: AtomicUsize = AtomicUsize::new(0);
+static SAVE_LOCK: LazyLock<Mutex<usize>> = LazyLock::new(|| Mutex::new(0));
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ActivityCache {
     pub activities: Vec<CompletedActivity>,
@@ -15,6 +37,26 @@ pub struct ActivityCache {
     pub profile_id: String,
     #[serde(default)]
     pub cache_version: u32,
+    /// Set when a rebuild was stopped while still paging, so older history may be missing
+    #[serde(default)]
+    pub partial: bool,
+    /// Set once older activities were left out by the retention cutoff or the per-profile cap
+    #[serde(default)]
+    pub trimmed: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileCacheStats {
+    pub activity_count: usize,
+    pub last_updated: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub profiles: HashMap<String, ProfileCacheStats>,
+    pub disk_size_bytes: u64,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -34,33 +76,48 @@ impl CacheManager {
 
     pub async fn load() -> Result<Self> {
         let cache_path = Self::get_cache_path()?;
-        
-        if !cache_path.exists() {
-            return Ok(Self::new());
-        }
+        let legacy_path = Self::get_legacy_cache_path()?;
 
-        let content = fs::read_to_string(&cache_path).await?;
+        let content = if cache_path.exists() {
+            match decompress(&fs::read(&cache_path).await?) {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!(target: "cache", "⚠️ Cache: Failed to decompress cache file: {}", e);
+                    let _ = fs::rename(&cache_path, cache_path.with_file_name("activity_cache.corrupt.json.gz")).await;
+                    return Ok(Self::new());
+                }
+            }
+        } else if legacy_path.exists() {
+            // One-time migration from the uncompressed cache format
+            let content = fs::read_to_string(&legacy_path).await?;
+
+            info!(target: "cache", "📦 Cache: Migrating uncompressed cache at {:?}", legacy_path);
+
+            write_atomic(&cache_path, &compress(&content)?).await?;
+            fs::remove_file(&legacy_path).await?;
+
+            content
+        } else {
+            return Ok(Self::new());
+        };
         
         match serde_json::from_str::<CacheManager>(&content) {
             Ok(cache) => {
                 // Check cache version
                 if cache.version != CACHE_VERSION {
-                    #[cfg(debug_assertions)]
-                    println!("🗑️ Cache: Invalidating old cache (version {} -> {})", cache.version, CACHE_VERSION);
-                    if let Err(_delete_err) = fs::remove_file(&cache_path).await {
-                        #[cfg(debug_assertions)]
-                        println!("⚠️ Cache: Failed to delete old cache file: {}", _delete_err);
+                    info!(target: "cache", "🗑️ Cache: Invalidating old cache (version {} -> {})", cache.version, CACHE_VERSION);
+                    if let Err(delete_err) = fs::remove_file(&cache_path).await {
+                        warn!(target: "cache", "⚠️ Cache: Failed to delete old cache file: {}", delete_err);
                     }
                     return Ok(Self::new());
                 }
 
                 // Check individual profile cache versions
                 let mut valid_cache = cache;
-                valid_cache.profiles.retain(|_profile_id, activity_cache| {
+                valid_cache.profiles.retain(|profile_id, activity_cache| {
                     if activity_cache.cache_version != CACHE_VERSION {
-                        #[cfg(debug_assertions)]
-                        println!("🗑️ Cache: Removing outdated cache for profile {} (version {} -> {})",
-                            _profile_id, activity_cache.cache_version, CACHE_VERSION);
+                        info!(target: "cache", "🗑️ Cache: Removing outdated cache for profile {} (version {} -> {})",
+                            profile_id, activity_cache.cache_version, CACHE_VERSION);
                         false
                     } else {
                         true
@@ -69,16 +126,47 @@ impl CacheManager {
                 
                 Ok(valid_cache)
             }
-            Err(_e) => {
-                #[cfg(debug_assertions)]
-                println!("🗑️ Cache: Removing incompatible cache file due to schema change: {}", _e);
-                if let Err(_delete_err) = fs::remove_file(&cache_path).await {
-                    #[cfg(debug_assertions)]
-                    println!("⚠️ Cache: Failed to delete old cache file: {}", _delete_err);
+            Err(e) => {
+                warn!(target: "cache", "⚠️ Cache: Failed to parse cache file, attempting recovery: {}", e);
+
+                let recovered = Self::recover(&content);
+
+                info!(target: "cache", "🩹 Cache: Recovered {} profiles from unreadable cache", recovered.profiles.len());
+
+                let backup_path = cache_path.with_file_name("activity_cache.corrupt.json");
+                if let Err(backup_err) = fs::write(&backup_path, &content).await {
+                    warn!(target: "cache", "⚠️ Cache: Failed to back up unreadable cache file: {}", backup_err);
+                } else {
+                    let _ = fs::remove_file(&cache_path).await;
+                }
+
+                Ok(recovered)
+            }
+        }
+    }
+
+    /// Best-effort recovery that keeps every profile entry which still parses on its own.
+    fn recover(content: &str) -> Self {
+        let mut recovered = Self::new();
+
+        let profiles = match serde_json::from_str::<serde_json::Value>(content) {
+            Ok(serde_json::Value::Object(mut root)) => match root.remove("profiles") {
+                Some(serde_json::Value::Object(profiles)) => profiles,
+                _ => return recovered,
+            },
+            _ => return recovered,
+        };
+
+        for (profile_id, value) in profiles {
+            match serde_json::from_value::<ActivityCache>(value) {
+                Ok(cache) if cache.cache_version == CACHE_VERSION => {
+                    recovered.profiles.insert(profile_id, cache);
                 }
-                Ok(Self::new())
+                _ => (),
             }
         }
+
+        recovered
     }
 
     #[allow(dead_code)]
@@ -89,84 +177,163 @@ impl CacheManager {
             fs::create_dir_all(parent).await?;
         }
 
-        let content = serde_json::to_string(self)?;
-        fs::write(&cache_path, content).await?;
+        let content = compress(&serde_json::to_string(self)?)?;
+        let generation = SAVE_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
 
-        #[cfg(debug_assertions)]
-        {
-            let profile_count = self.profiles.len();
-            let total_activities: usize = self.profiles.values().map(|c| c.activities.len()).sum();
-            println!("💾 Cache: Saved cache to {:?} with {} profiles and {} total activities", cache_path, profile_count, total_activities);
-        }
+        write_in_order(&cache_path, &content, generation).await?;
+
+        let total_activities: usize = self.profiles.values().map(|c| c.activities.len()).sum();
+        info!(target: "cache", "💾 Cache: Saved cache to {:?} with {} profiles and {} total activities", cache_path, self.profiles.len(), total_activities);
 
         Ok(())
     }
 
     /// Serialize now, write to disk in a background task (non-blocking).
     pub fn save_in_background(&self) {
-        let content = match serde_json::to_string(self) {
+        let content = match serde_json::to_string(self).map_err(anyhow::Error::from).and_then(|c| compress(&c)) {
             Ok(c) => c,
-            Err(_e) => {
-                #[cfg(debug_assertions)]
-                eprintln!("❌ Cache: Failed to serialize cache: {}", _e);
+            Err(e) => {
+                error!(target: "cache", "❌ Cache: Failed to serialize cache: {}", e);
                 return;
             }
         };
         let cache_path = match Self::get_cache_path() {
             Ok(p) => p,
-            Err(_e) => {
-                #[cfg(debug_assertions)]
-                eprintln!("❌ Cache: Failed to get cache path: {}", _e);
+            Err(e) => {
+                error!(target: "cache", "❌ Cache: Failed to get cache path: {}", e);
                 return;
             }
         };
 
-        #[cfg(debug_assertions)]
-        {
-            let profile_count = self.profiles.len();
-            let total_activities: usize = self.profiles.values().map(|c| c.activities.len()).sum();
-            println!("💾 Cache: Queuing background save with {} profiles and {} total activities", profile_count, total_activities);
-        }
+        let generation = SAVE_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+
+        let total_activities: usize = self.profiles.values().map(|c| c.activities.len()).sum();
+        debug!(target: "cache", "💾 Cache: Queuing background save with {} profiles and {} total activities", self.profiles.len(), total_activities);
 
         tokio::spawn(async move {
             if let Some(parent) = cache_path.parent() {
                 let _ = fs::create_dir_all(parent).await;
             }
-            if let Err(_e) = fs::write(&cache_path, content).await {
-                #[cfg(debug_assertions)]
-                eprintln!("❌ Cache: Background save failed: {}", _e);
+            if let Err(e) = write_in_order(&cache_path, &content, generation).await {
+                error!(target: "cache", "❌ Cache: Background save failed: {}", e);
             }
         });
     }
 
+    /// Every cached profile as plain JSON, in the same shape as the cache file. Serialized up
+    /// front, so `write_export` can write it out without holding the cache lock.
+    pub fn export_snapshot(&self) -> Result<String> {
+        let total_activities: usize = self.profiles.values().map(|c| c.activities.len()).sum();
+        debug!(target: "cache", "📤 Cache: Exporting {} profiles and {} total activities", self.profiles.len(), total_activities);
+
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub async fn write_export(path: &Path, snapshot: String) -> Result<()> {
+        fs::write(path, snapshot).await?;
+
+        info!(target: "cache", "📤 Cache: Exported cache to {:?}", path);
+
+        Ok(())
+    }
+
+    /// Reads a backup written by `write_export`. Backups from older versions load with any newer
+    /// fields defaulted, and the activities missing PGCR data get it refetched after the import.
+    /// A backup from a newer version is rejected, as it may not mean the same thing here.
+    pub async fn read_export(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).await?;
+        let mut imported = serde_json::from_str::<CacheManager>(&content)?;
+
+        if imported.version > CACHE_VERSION {
+            bail!(
+                "Backup is from a newer version of the cache ({} instead of {})",
+                imported.version,
+                CACHE_VERSION
+            );
+        }
+
+        imported
+            .profiles
+            .retain(|_, cache| cache.cache_version <= CACHE_VERSION);
+
+        Ok(imported)
+    }
+
+    /// Adds a backup's profiles, merging them into what's cached unless `replace` is set, in
+    /// which case the cache is dropped first. Returns the number of activities imported.
+    pub fn import(&mut self, imported: CacheManager, replace: bool) -> usize {
+        if replace {
+            self.clear_cache();
+        }
+
+        let mut count = 0;
+
+        for (profile_id, cache) in imported.profiles {
+            count += cache.activities.len();
+            self.merge_activities(profile_id, cache.activities);
+        }
+
+        info!(target: "cache", "📥 Cache: Imported {} activities (replace: {})", count, replace);
+
+        count
+    }
+
     pub fn get_cached_activities(&self, profile_id: &str) -> Option<&ActivityCache> {
         self.profiles.get(profile_id)
     }
 
-    pub fn update_cache(&mut self, profile_id: String, activities: Vec<CompletedActivity>) {
+    pub fn update_cache(&mut self, profile_id: String, mut activities: Vec<CompletedActivity>) {
+        dedup_activities(&mut activities);
+        activities.sort_by(|a, b| b.period.cmp(&a.period));
+
+        let truncated = activities.len() > MAX_CACHED_ACTIVITIES_PER_PROFILE;
+        activities.truncate(MAX_CACHED_ACTIVITIES_PER_PROFILE);
+
         let cache = ActivityCache {
             activities,
             last_updated: Utc::now(),
             profile_id: profile_id.clone(),
             cache_version: CACHE_VERSION,
+            partial: self.is_partial(&profile_id),
+            trimmed: truncated || self.is_trimmed(&profile_id),
         };
         
         self.version = CACHE_VERSION;
         self.profiles.insert(profile_id, cache);
+        self.evict_profiles();
     }
 
     pub fn merge_activities(&mut self, profile_id: String, new_activities: Vec<CompletedActivity>) {
         if let Some(existing_cache) = self.profiles.get_mut(&profile_id) {
-            for new_activity in new_activities {
-                if !existing_cache.activities.iter().any(|existing| {
-                    existing.instance_id == new_activity.instance_id &&
-                    existing.period == new_activity.period
-                }) {
-                    existing_cache.activities.push(new_activity);
+            let mut positions: HashMap<String, usize> = existing_cache
+                .activities
+                .iter()
+                .enumerate()
+                .map(|(i, a)| (a.instance_id.clone(), i))
+                .collect();
+
+            // The entry with the newer PGCR data wins, the existing one if they're level
+            for activity in new_activities {
+                match positions.get(&activity.instance_id) {
+                    Some(&i) => {
+                        if activity.pgcr_version > existing_cache.activities[i].pgcr_version {
+                            existing_cache.activities[i] = activity;
+                        }
+                    }
+                    None => {
+                        positions.insert(activity.instance_id.clone(), existing_cache.activities.len());
+                        existing_cache.activities.push(activity);
+                    }
                 }
             }
 
             existing_cache.activities.sort_by(|a, b| b.period.cmp(&a.period));
+
+            if existing_cache.activities.len() > MAX_CACHED_ACTIVITIES_PER_PROFILE {
+                existing_cache.activities.truncate(MAX_CACHED_ACTIVITIES_PER_PROFILE);
+                existing_cache.trimmed = true;
+            }
+
             existing_cache.last_updated = Utc::now();
             existing_cache.cache_version = CACHE_VERSION;
             self.version = CACHE_VERSION;
@@ -175,6 +342,81 @@ impl CacheManager {
         }
     }
 
+    pub fn is_partial(&self, profile_id: &str) -> bool {
+        self.profiles.get(profile_id).map_or(false, |c| c.partial)
+    }
+
+    pub fn set_partial(&mut self, profile_id: &str, partial: bool) {
+        if let Some(cache) = self.profiles.get_mut(profile_id) {
+            cache.partial = partial;
+        }
+    }
+
+    pub fn is_trimmed(&self, profile_id: &str) -> bool {
+        self.profiles.get(profile_id).map_or(false, |c| c.trimmed)
+    }
+
+    pub fn set_trimmed(&mut self, profile_id: &str) {
+        if let Some(cache) = self.profiles.get_mut(profile_id) {
+            cache.trimmed = true;
+        }
+    }
+
+    /// Copies PGCR data fetched for `activities` onto the matching cached entries, leaving the
+    /// rest of the profile's cache as it is now. Activities no longer cached aren't added back.
+    pub fn merge_pgcr_data(&mut self, profile_id: &str, activities: &[CompletedActivity]) {
+        let cache = match self.profiles.get_mut(profile_id) {
+            Some(c) => c,
+            None => return,
+        };
+
+        let fetched: HashMap<&str, &CompletedActivity> = activities
+            .iter()
+            .filter(|a| a.pgcr_version > 0)
+            .map(|a| (a.instance_id.as_str(), a))
+            .collect();
+
+        for cached in cache.activities.iter_mut() {
+            match fetched.get(cached.instance_id.as_str()) {
+                Some(activity) if activity.pgcr_version > cached.pgcr_version => {
+                    *cached = (*activity).clone();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    pub fn prune_activities_before(&mut self, profile_id: &str, cutoff: DateTime<Utc>) {
+        if let Some(cache) = self.profiles.get_mut(profile_id) {
+            let before = cache.activities.len();
+            cache.activities.retain(|a| a.period >= cutoff);
+
+            if cache.activities.len() < before {
+                cache.trimmed = true;
+                info!(target: "cache", "✂️ Cache: Pruned {} activities older than {}", before - cache.activities.len(), cutoff);
+            }
+        }
+    }
+
+    /// Drops the least recently updated profiles once more than `MAX_CACHED_PROFILES` are stored.
+    fn evict_profiles(&mut self) {
+        while self.profiles.len() > MAX_CACHED_PROFILES {
+            let oldest = self
+                .profiles
+                .iter()
+                .min_by_key(|(_, cache)| cache.last_updated)
+                .map(|(id, _)| id.clone());
+
+            match oldest {
+                Some(id) => {
+                    info!(target: "cache", "🗑️ Cache: Evicting least recently updated profile {}", id);
+                    self.profiles.remove(&id);
+                }
+                None => break,
+            }
+        }
+    }
+
     #[allow(dead_code)]
     pub fn should_refresh_cache(&self, profile_id: &str, max_age_hours: i64) -> bool {
         if let Some(cache) = self.profiles.get(profile_id) {
@@ -221,28 +463,31 @@ impl CacheManager {
     }
 
     fn get_cache_path() -> Result<PathBuf> {
-        let mut path = dirs::config_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+        let mut path = get_cache_dir()?;
+        path.push("activity_cache.json.gz");
         
-        path.push("threepole");
+        Ok(path)
+    }
+
+    fn get_legacy_cache_path() -> Result<PathBuf> {
+        let mut path = get_cache_dir()?;
         path.push("activity_cache.json");
-        
+
         Ok(path)
     }
 
-    #[allow(dead_code)]
     pub fn clear_cache(&mut self) {
         self.profiles.clear();
     }
 
-    #[allow(dead_code)]
     pub async fn clear_cache_directory() -> Result<()> {
         let cache_path = Self::get_cache_path()?;
         
-        if cache_path.exists() {
-            fs::remove_file(&cache_path).await?;
-            #[cfg(debug_assertions)]
-            println!("🗑️ Cache: Removed cache file at {:?}", cache_path);
+        for path in [&cache_path, &Self::get_legacy_cache_path()?] {
+            if path.exists() {
+                fs::remove_file(path).await?;
+                info!(target: "cache", "🗑️ Cache: Removed cache file at {:?}", path);
+            }
         }
         
         if let Some(parent) = cache_path.parent() {
@@ -255,12 +500,10 @@ impl CacheManager {
                     }
                     
                     if !has_files {
-                        if let Err(_e) = fs::remove_dir(parent).await {
-                            #[cfg(debug_assertions)]
-                            println!("⚠️ Cache: Could not remove empty cache directory: {}", _e);
+                        if let Err(e) = fs::remove_dir(parent).await {
+                            warn!(target: "cache", "⚠️ Cache: Could not remove empty cache directory: {}", e);
                         } else {
-                            #[cfg(debug_assertions)]
-                            println!("🗑️ Cache: Removed empty cache directory at {:?}", parent);
+                            info!(target: "cache", "🗑️ Cache: Removed empty cache directory at {:?}", parent);
                         }
                     }
                 }
@@ -270,15 +513,230 @@ impl CacheManager {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn remove_profile_cache(&mut self, profile_id: &str) {
         self.profiles.remove(profile_id);
     }
 
-    #[allow(dead_code)]
-    pub fn get_cache_stats(&self) -> HashMap<String, (usize, DateTime<Utc>)> {
+    pub fn get_cache_stats(&self) -> HashMap<String, ProfileCacheStats> {
         self.profiles.iter().map(|(id, cache)| {
-            (id.clone(), (cache.activities.len(), cache.last_updated))
+            (id.clone(), ProfileCacheStats {
+                activity_count: cache.activities.len(),
+                last_updated: cache.last_updated,
+            })
         }).collect()
     }
-}
\ No newline at end of file
+
+    /// Size of the cache file on disk, 0 if it hasn't been written yet
+    pub async fn get_cache_disk_size() -> u64 {
+        match Self::get_cache_path() {
+            Ok(path) => fs::metadata(path).await.map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        }
+    }
+}
+
+/// Activity definitions fetched from the manifest, stamped with the manifest version they came from.
+#[derive(Serialize, Deserialize, Default)]
+pub struct DefinitionCache {
+    pub manifest_version: Option<String>,
+    pub activities: HashMap<usize, ActivityInfo>,
+}
+
+impl DefinitionCache {
+    pub async fn load() -> Result<Self> {
+        let cache_path = Self::get_cache_path()?;
+
+        if !cache_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&cache_path).await?;
+
+        match serde_json::from_str::<DefinitionCache>(&content) {
+            Ok(cache) => Ok(cache),
+            Err(e) => {
+                info!(target: "definitions", "🗑️ Definitions: Discarding unreadable definition cache: {}", e);
+                Ok(Self::default())
+            }
+        }
+    }
+
+    /// Serialize now, write to disk in a background task (non-blocking).
+    pub fn save_in_background(&self) {
+        let content = match serde_json::to_string(self) {
+            Ok(c) => c,
+            Err(e) => {
+                error!(target: "definitions", "❌ Definitions: Failed to serialize definition cache: {}", e);
+                return;
+            }
+        };
+        let cache_path = match Self::get_cache_path() {
+            Ok(p) => p,
+            Err(e) => {
+                error!(target: "definitions", "❌ Definitions: Failed to get cache path: {}", e);
+                return;
+            }
+        };
+
+        tokio::spawn(async move {
+            if let Some(parent) = cache_path.parent() {
+                let _ = fs::create_dir_all(parent).await;
+            }
+            if let Err(e) = write_atomic(&cache_path, content.as_bytes()).await {
+                error!(target: "definitions", "❌ Definitions: Background save failed: {}", e);
+            }
+        });
+    }
+
+    fn get_cache_path() -> Result<PathBuf> {
+        let mut path = get_cache_dir()?;
+        path.push("activity_definitions.json");
+
+        Ok(path)
+    }
+}
+
+/// Writes the snapshot unless a newer one has already been saved. See `SAVE_GENERATION`.
+async fn write_in_order(path: &Path, content: &[u8], generation: usize) -> std::io::Result<()> {
+    let mut last_saved = SAVE_LOCK.lock().await;
+
+    if *last_saved > generation {
+        debug!(target: "cache", "⏭️ Cache: Skipping save of snapshot {}, snapshot {} is already on disk", generation, *last_saved);
+
+        return Ok(());
+    }
+
+    write_atomic(path, content).await?;
+    *last_saved = generation;
+
+    Ok(())
+}
+
+/// Writes to a temporary sibling file first so an interrupted write never leaves a truncated cache.
+async fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let tmp_path = path.with_extension(format!(
+        "tmp{}",
+        WRITE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    fs::write(&tmp_path, content).await?;
+    fs::rename(&tmp_path, path).await
+}
+
+fn compress(content: &str) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content.as_bytes())?;
+
+    Ok(encoder.finish()?)
+}
+
+fn decompress(bytes: &[u8]) -> Result<String> {
+    let mut content = String::new();
+    GzDecoder::new(bytes).read_to_string(&mut content)?;
+
+    Ok(content)
+}
+
+/// Keeps the first entry per instance. The same instance is listed under every character that
+/// took part in it (e.g. swapping characters mid-run), so it must only be counted once.
+pub fn dedup_activities(activities: &mut Vec<CompletedActivity>) {
+    let mut seen = HashSet::new();
+    activities.retain(|a| seen.insert(a.instance_id.clone()));
+}
+
+pub fn get_profile_id(profile: &Profile) -> String {
+    format!("{}_{}", profile.account_platform, profile.account_id)
+}
+
+fn get_cache_dir() -> Result<PathBuf> {
+    let mut path = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    path.push("threepole");
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity(instance_id: &str, pgcr_version: u32) -> CompletedActivity {
+        serde_json::from_value(serde_json::json!({
+            "period": "2024-01-10T12:00:00Z",
+            "instanceId": instance_id,
+            "activityHash": 1,
+            "modes": [4],
+            "completed": true,
+            "activityDuration": "30m 0s",
+            "activityDurationSeconds": 1800,
+            "pgcrVersion": pgcr_version,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merge_prefers_newer_pgcr_data() {
+        let mut cache_manager = CacheManager::new();
+        cache_manager.update_cache("profile".to_string(), vec![activity("a", 0), activity("b", 1)]);
+        cache_manager.merge_activities(
+            "profile".to_string(),
+            vec![activity("a", 1), activity("b", 0), activity("c", 0)],
+        );
+
+        let mut merged: Vec<(String, u32)> = cache_manager.profiles["profile"]
+            .activities
+            .iter()
+            .map(|a| (a.instance_id.clone(), a.pgcr_version))
+            .collect();
+        merged.sort();
+
+        assert_eq!(
+            merged,
+            [("a".to_string(), 1), ("b".to_string(), 1), ("c".to_string(), 0)]
+        );
+    }
+
+    async fn read_export_with_version(version: u32) -> Result<CacheManager> {
+        let path = std::env::temp_dir().join(format!("threepole-export-test-{version}.json"));
+        let export = serde_json::json!({
+            "version": version,
+            "profiles": {
+                "profile": {
+                    "activities": [{
+                        "period": "2024-01-10T12:00:00Z",
+                        "instanceId": "a",
+                        "activityHash": 1,
+                        "modes": [4],
+                        "completed": true,
+                        "activityDuration": "30m 0s",
+                        "activityDurationSeconds": 1800,
+                    }],
+                    "last_updated": "2024-01-10T12:00:00Z",
+                    "profile_id": "profile",
+                    "cache_version": version,
+                },
+            },
+        });
+
+        fs::write(&path, export.to_string()).await.unwrap();
+        let res = CacheManager::read_export(&path).await;
+        let _ = fs::remove_file(&path).await;
+
+        res
+    }
+
+    #[tokio::test]
+    async fn reads_older_export_with_defaults() {
+        let imported = read_export_with_version(CACHE_VERSION - 1).await.unwrap();
+        let activity = &imported.profiles["profile"].activities[0];
+
+        assert_eq!(activity.fireteam_size, None);
+        assert!(!activity.pgcr_fetched());
+    }
+
+    #[tokio::test]
+    async fn rejects_newer_export() {
+        assert!(read_export_with_version(CACHE_VERSION + 1).await.is_err());
+    }
+}
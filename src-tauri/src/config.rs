@@ -7,12 +7,22 @@ use std::{
 use anyhow::{anyhow, Result};
 use directories::BaseDirs;
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tracing::info;
 
 use self::{preferences::Preferences, profiles::Profiles};
 use crate::consts::APP_NAME;
 
+// Stored alongside each file's fields; unknown to the structs, so serde ignores it
+const SCHEMA_VERSION_KEY: &str = "schemaVersion";
+
+/// Upgrades the raw JSON of a config file by one schema version
+type Migration = fn(&mut Value);
+
+pub mod auth;
 pub mod preferences;
 pub mod profiles;
+pub mod window_state;
 
 pub struct ConfigManager {
     preferences: Preferences,
@@ -21,8 +31,13 @@ pub struct ConfigManager {
 
 impl ConfigManager {
     pub fn load() -> Result<Self> {
+        let mut preferences = Preferences::load()?;
+
+        // A hand-edited file skips set_preferences, and a limit of 0 would stall every fetch
+        preferences.clamp_limits();
+
         Ok(Self {
-            preferences: Preferences::load()?,
+            preferences,
             profiles: Profiles::load()?,
         })
     }
@@ -35,7 +50,10 @@ impl ConfigManager {
         &self.profiles
     }
 
-    pub fn set_preferences(&mut self, preferences: Preferences) -> Result<()> {
+    pub fn set_preferences(&mut self, mut preferences: Preferences) -> Result<()> {
+        preferences.validate()?;
+        preferences.clamp_limits();
+
         self.preferences = preferences;
         self.preferences.write()
     }
@@ -50,7 +68,10 @@ trait ConfigFile: Serialize + DeserializeOwned + Default {
     fn load() -> Result<Self> {
         match read_to_string(Self::get_path()?) {
             Ok(s) => {
-                let def = serde_json::from_str::<Self>(&s)?;
+                let mut value = serde_json::from_str::<Value>(&s)?;
+                Self::migrate(&mut value);
+
+                let def = serde_json::from_value::<Self>(value)?;
                 def.write()?;
                 Ok(def)
             }
@@ -72,7 +93,33 @@ trait ConfigFile: Serialize + DeserializeOwned + Default {
 
         create_dir_all(dir)?;
 
-        Ok(std::fs::write(path, serde_json::to_string(&self)?)?)
+        let mut value = serde_json::to_value(self)?;
+
+        if let Some(object) = value.as_object_mut() {
+            object.insert(SCHEMA_VERSION_KEY.to_string(), Self::migrations().len().into());
+        }
+
+        Ok(std::fs::write(path, serde_json::to_string(&value)?)?)
+    }
+
+    /// Runs every migration newer than the file's schema version, oldest first. Files from
+    /// before versioning count as version 0.
+    fn migrate(value: &mut Value) {
+        let version = value
+            .get(SCHEMA_VERSION_KEY)
+            .and_then(Value::as_u64)
+            .unwrap_or(0) as usize;
+
+        for (index, migration) in Self::migrations().iter().enumerate().skip(version) {
+            info!(target: "config", "🔧 Config: Migrating {} to schema version {}", Self::get_filename(), index + 1);
+
+            migration(value);
+        }
+    }
+
+    /// Ordered upgrades for older files. Only ever append, the schema version is the count.
+    fn migrations() -> &'static [Migration] {
+        &[]
     }
 
     fn get_path() -> Result<PathBuf> {
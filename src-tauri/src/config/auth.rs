@@ -0,0 +1,29 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::ConfigFile;
+use crate::api::auth::AuthToken;
+
+/// The signed-in user's OAuth session, kept out of profiles.json so that stays safe to share
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct TokenStore {
+    pub token: Option<AuthToken>,
+}
+
+impl TokenStore {
+    pub fn read() -> Result<Self> {
+        Self::load()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.write()
+    }
+}
+
+impl ConfigFile for TokenStore {
+    fn get_filename() -> &'static str {
+        "auth.json"
+    }
+}
@@ -1,7 +1,16 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use super::ConfigFile;
+use super::{ConfigFile, Migration};
+use crate::consts::{
+    ACTIVITY_FETCH_CONCURRENCY, API_RATE_LIMIT_PER_SEC, LOCAL_SERVER_PORT,
+    MAX_ACTIVITY_FETCH_CONCURRENCY, MAX_API_RATE_LIMIT_PER_SEC, MAX_OVERLAY_SCALE,
+    MAX_PGCR_FETCH_CONCURRENCY, MIN_OVERLAY_SCALE, MIN_POLL_INTERVAL_SECS, PGCR_FETCH_CONCURRENCY,
+    POLLER_HISTORY_CHECK_INTERVAL, POLLER_INTERVAL_SECS,
+};
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -28,7 +37,59 @@ impl Default for ColorPreferences {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+impl ColorPreferences {
+    pub fn validate(&self) -> Result<()> {
+        // Walk the serialized fields so colors added later are validated too
+        let fields = match serde_json::to_value(self)? {
+            Value::Object(o) => o,
+            _ => return Ok(()),
+        };
+
+        for (name, value) in fields {
+            match value.as_str() {
+                Some(color) if is_valid_color(color) => (),
+                _ => bail!("Invalid color for {name}: {value}"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_valid_color(color: &str) -> bool {
+    let color = color.trim();
+
+    if let Some(hex) = color.strip_prefix('#') {
+        return (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+
+    let (components, has_alpha) = if let Some(c) = color.strip_prefix("rgba(") {
+        (c, true)
+    } else if let Some(c) = color.strip_prefix("rgb(") {
+        (c, false)
+    } else {
+        return false;
+    };
+
+    let components: Vec<&str> = match components.strip_suffix(')') {
+        Some(c) => c.split(',').map(|s| s.trim()).collect(),
+        None => return false,
+    };
+
+    if components.len() != if has_alpha { 4 } else { 3 } {
+        return false;
+    }
+
+    let channels_valid = components[..3].iter().all(|c| c.parse::<u8>().is_ok());
+    let alpha_valid = !has_alpha
+        || components[3]
+            .parse::<f32>()
+            .map_or(false, |a| (0.0..=1.0).contains(&a));
+
+    channels_valid && alpha_valid
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct FilterPreferences {
@@ -36,12 +97,20 @@ pub struct FilterPreferences {
     pub show_dungeons: bool,
     pub show_strikes: bool,
     pub show_lost_sectors: bool,
+    pub show_nightfalls: bool,
+    pub show_crucible: bool,
+    pub show_trials: bool,
     pub show_completed: bool,
     pub show_incomplete: bool,
     pub show_fresh_start: bool,
     pub show_checkpoint: bool,
+    pub show_flawless_only: bool,
+    /// Hides activities from before the current season started
+    pub current_season_only: bool,
     pub min_duration_seconds: Option<u32>,
     pub max_duration_seconds: Option<u32>,
+    pub min_fireteam_size: Option<u8>,
+    pub max_fireteam_size: Option<u8>,
     pub specific_raids: HashMap<u32, bool>,
     pub specific_dungeons: HashMap<u32, bool>,
 }
@@ -53,19 +122,26 @@ impl Default for FilterPreferences {
             show_dungeons: true,
             show_strikes: true,
             show_lost_sectors: true,
+            show_nightfalls: true,
+            show_crucible: true,
+            show_trials: true,
             show_completed: true,
             show_incomplete: true,
             show_fresh_start: true,
             show_checkpoint: true,
+            show_flawless_only: false,
+            current_season_only: false,
             min_duration_seconds: None,
             max_duration_seconds: None,
+            min_fireteam_size: None,
+            max_fireteam_size: None,
             specific_raids: HashMap::new(),
             specific_dungeons: HashMap::new(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct SortPreferences {
@@ -74,6 +150,54 @@ pub struct SortPreferences {
     pub time_range: String,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TimeRange {
+    Daily,
+    Weekly,
+    Monthly,
+    All,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Time,
+    Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortPreferences {
+    /// Unknown keys (including the UI-only "activity") fall back to sorting by time
+    pub fn sort_key(&self) -> SortKey {
+        match self.sort_by.as_str() {
+            "duration" => SortKey::Duration,
+            _ => SortKey::Time,
+        }
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        match self.sort_order.as_str() {
+            "asc" => SortOrder::Ascending,
+            _ => SortOrder::Descending,
+        }
+    }
+
+    /// Accepts both the UI's values ("today", "week", "month") and the longer spellings.
+    /// Anything unrecognised shows the whole history.
+    pub fn time_range(&self) -> TimeRange {
+        match self.time_range.as_str() {
+            "today" | "daily" => TimeRange::Daily,
+            "week" | "weekly" => TimeRange::Weekly,
+            "month" | "monthly" => TimeRange::Monthly,
+            _ => TimeRange::All,
+        }
+    }
+}
+
 impl Default for SortPreferences {
     fn default() -> Self {
         Self {
@@ -84,35 +208,214 @@ impl Default for SortPreferences {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum OverlayPosition {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Custom {
+        x: i32,
+        y: i32,
+    },
+}
+
+/// A stat the overlay can show, in the order the user listed them
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WidgetKind {
+    Timer,
+    DailyClears,
+    WeeklyClears,
+    PersonalBest,
+    Streak,
+    ResetCountdown,
+    Fireteam,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct Preferences {
     pub enable_overlay: bool,
-    pub display_daily_clears: bool,
+    pub overlay_widgets: Vec<WidgetKind>,
     pub display_clear_notifications: bool,
+    /// Built-in sound name or audio file path played on new clears
+    pub notification_sound: Option<String>,
     pub display_milliseconds: bool,
+    pub poll_interval_seconds: u32,
+    pub history_check_multiplier: u32,
+    pub slow_poll_when_game_closed: bool,
+    pub overlay_position: OverlayPosition,
+    pub compact_overlay: bool,
+    /// Which of the available monitors the overlay is pinned to, the primary one if it's out of
+    /// range. `None` follows the game window instead.
+    pub overlay_monitor_index: Option<usize>,
+    pub overlay_scale: f32,
+    pub toggle_overlay_hotkey: Option<String>,
+    pub enable_discord_presence: bool,
+    pub clear_webhook_url: Option<String>,
+    pub fetch_concurrency: usize,
+    pub pgcr_concurrency: usize,
+    /// Shared by every Bungie request, on top of the concurrency limits
+    pub requests_per_second: u32,
+    pub history_retention_days: Option<u32>,
+    pub enable_local_server: bool,
+    pub local_server_port: u16,
+    /// EnvFilter directive for the log file, e.g. "debug" or "pgcr=debug,info"
+    pub log_level: Option<String>,
     pub colors: ColorPreferences,
     pub filters: FilterPreferences,
     pub sorting: SortPreferences,
+    /// Named filter and sort combinations that can be swapped in as the active ones
+    pub views: HashMap<String, (FilterPreferences, SortPreferences)>,
 }
 
 impl Default for Preferences {
     fn default() -> Self {
         Self {
             enable_overlay: false,
-            display_daily_clears: true,
+            overlay_widgets: vec![WidgetKind::Timer, WidgetKind::DailyClears],
             display_clear_notifications: true,
+            notification_sound: None,
             display_milliseconds: false,
+            poll_interval_seconds: POLLER_INTERVAL_SECS as u32,
+            history_check_multiplier: POLLER_HISTORY_CHECK_INTERVAL as u32,
+            slow_poll_when_game_closed: true,
+            overlay_position: OverlayPosition::default(),
+            compact_overlay: false,
+            overlay_monitor_index: None,
+            overlay_scale: 1.0,
+            toggle_overlay_hotkey: None,
+            enable_discord_presence: false,
+            clear_webhook_url: None,
+            fetch_concurrency: ACTIVITY_FETCH_CONCURRENCY,
+            pgcr_concurrency: PGCR_FETCH_CONCURRENCY,
+            requests_per_second: API_RATE_LIMIT_PER_SEC,
+            history_retention_days: None,
+            enable_local_server: false,
+            local_server_port: LOCAL_SERVER_PORT,
+            log_level: None,
             colors: ColorPreferences::default(),
             filters: FilterPreferences::default(),
             sorting: SortPreferences::default(),
+            views: HashMap::new(),
+        }
+    }
+}
+
+impl Preferences {
+    pub fn validate(&self) -> Result<()> {
+        self.colors.validate()
+    }
+
+    /// Keeps the advanced request limits within what Bungie tolerates, and the overlay scale
+    /// within what stays readable.
+    pub fn clamp_limits(&mut self) {
+        self.fetch_concurrency = self.fetch_concurrency.clamp(1, MAX_ACTIVITY_FETCH_CONCURRENCY);
+        self.pgcr_concurrency = self.pgcr_concurrency.clamp(1, MAX_PGCR_FETCH_CONCURRENCY);
+        self.requests_per_second = self.requests_per_second.clamp(1, MAX_API_RATE_LIMIT_PER_SEC);
+
+        self.overlay_scale = match self.overlay_scale.is_finite() {
+            true => self.overlay_scale.clamp(MIN_OVERLAY_SCALE, MAX_OVERLAY_SCALE),
+            false => 1.0,
+        };
+    }
+
+    /// Stores the active filters and sorting under `name`, replacing any view already there
+    pub fn save_view(&mut self, name: String) {
+        self.views.insert(name, (self.filters.clone(), self.sorting.clone()));
+    }
+
+    pub fn delete_view(&mut self, name: &str) -> bool {
+        self.views.remove(name).is_some()
+    }
+
+    /// Makes the view's filters and sorting the active ones. Returns `false` if there's no such view
+    pub fn apply_view(&mut self, name: &str) -> bool {
+        match self.views.get(name) {
+            Some((filters, sorting)) => {
+                self.filters = filters.clone();
+                self.sorting = sorting.clone();
+                true
+            }
+            None => false,
         }
     }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_seconds.max(MIN_POLL_INTERVAL_SECS) as u64)
+    }
+
+    pub fn history_check_interval(&self) -> usize {
+        self.history_check_multiplier.max(1) as usize
+    }
+
+    /// Activities that started before this are dropped from the cache, `None` keeps everything
+    pub fn history_cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.history_retention_days
+            .filter(|days| *days > 0)
+            .map(|days| now - chrono::Duration::days(days as i64))
+    }
 }
 
 impl ConfigFile for Preferences {
     fn get_filename() -> &'static str {
         "preferences.json"
     }
+
+    fn migrations() -> &'static [Migration] {
+        &[normalize_time_range, overlay_widgets_from_flags]
+    }
+}
+
+/// Version 1: the backend also accepted "daily"/"weekly"/"monthly", which the UI doesn't
+/// know, so store the UI's spellings instead
+fn normalize_time_range(value: &mut Value) {
+    let range = match value.pointer_mut("/sorting/timeRange") {
+        Some(r) => r,
+        None => return,
+    };
+
+    let normalized = match range.as_str() {
+        Some("daily") => "today",
+        Some("weekly") => "week",
+        Some("monthly") => "month",
+        _ => return,
+    };
+
+    *range = normalized.into();
+}
+
+/// Version 2: the overlay's stats were separate display flags, now they're one ordered list
+fn overlay_widgets_from_flags(value: &mut Value) {
+    let object = match value.as_object_mut() {
+        Some(o) => o,
+        None => return,
+    };
+
+    // Flags missing from the file had these defaults
+    let flags = [
+        ("displayDailyClears", true, "dailyClears"),
+        ("displayResetCountdown", false, "resetCountdown"),
+        ("displayFireteam", false, "fireteam"),
+        ("displayStreak", false, "streak"),
+    ];
+
+    let mut widgets = vec![Value::from("timer")];
+
+    for (flag, default, widget) in flags {
+        let enabled = object
+            .remove(flag)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(default);
+
+        if enabled {
+            widgets.push(widget.into());
+        }
+    }
+
+    object.insert("overlayWidgets".to_string(), widgets.into());
 }
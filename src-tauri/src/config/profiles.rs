@@ -9,6 +9,20 @@ use super::ConfigFile;
 pub struct Profiles {
     pub saved_profiles: Vec<Profile>,
     pub selected_profile: Option<Profile>,
+    /// Saved profiles whose clears are counted alongside the selected one
+    pub tracked_profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    /// Tracked profiles other than the selected one, which is always tracked
+    pub fn other_tracked_profiles(&self) -> Vec<Profile> {
+        self.tracked_profiles
+            .iter()
+            .filter(|p| self.selected_profile.as_ref() != Some(*p))
+            .unique()
+            .cloned()
+            .collect()
+    }
 }
 
 impl<'de> Deserialize<'de> for Profiles {
@@ -21,12 +35,15 @@ impl<'de> Deserialize<'de> for Profiles {
         struct _Profiles {
             saved_profiles: Vec<Profile>,
             selected_profile: Option<Profile>,
+            #[serde(default)]
+            tracked_profiles: Vec<Profile>,
         }
 
         let profiles = _Profiles::deserialize(deserializer)?;
         Ok(Self {
             saved_profiles: profiles.saved_profiles.into_iter().unique().collect(),
             selected_profile: profiles.selected_profile,
+            tracked_profiles: profiles.tracked_profiles.into_iter().unique().collect(),
         })
     }
 }
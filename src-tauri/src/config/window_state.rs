@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tauri::{PhysicalPosition, Window};
+
+use super::ConfigFile;
+
+static WINDOW_STATES: LazyLock<Mutex<WindowStates>> =
+    LazyLock::new(|| Mutex::new(WindowStates::load().unwrap_or_default()));
+
+/// Where each window was last left, by label. Kept out of preferences.json since it changes
+/// every time a window is dragged.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct WindowStates {
+    windows: HashMap<String, WindowState>,
+}
+
+/// In logical pixels, so a window comes back at the same spot after a scaling change
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowState {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl ConfigFile for WindowStates {
+    fn get_filename() -> &'static str {
+        "windows.json"
+    }
+}
+
+pub fn get_window_state(label: &str) -> Option<WindowState> {
+    WINDOW_STATES.lock().unwrap().windows.get(label).copied()
+}
+
+/// Remembers `window`'s current placement in memory, `save_window_states` writes it out.
+/// Placements off every monitor (e.g. Windows parks minimized windows at -32000) are ignored.
+pub fn record_window_state(window: &Window) -> Result<()> {
+    let position = window.outer_position()?;
+
+    if !is_on_screen(window, position)? {
+        return Ok(());
+    }
+
+    let scale_factor = window.scale_factor()?;
+    let position = position.to_logical::<f64>(scale_factor);
+    let size = window.inner_size()?.to_logical::<f64>(scale_factor);
+
+    WINDOW_STATES.lock().unwrap().windows.insert(
+        window.label().to_string(),
+        WindowState {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        },
+    );
+
+    Ok(())
+}
+
+pub fn save_window_states() -> Result<()> {
+    WINDOW_STATES.lock().unwrap().write()
+}
+
+/// Whether `position` falls on one of the currently connected monitors
+pub fn is_on_screen(
+    window: &Window,
+    position: PhysicalPosition<i32>,
+) -> Result<bool, tauri::Error> {
+    Ok(window.available_monitors()?.iter().any(|m| {
+        let (origin, size) = (m.position(), m.size());
+
+        position.x >= origin.x
+            && position.y >= origin.y
+            && position.x < origin.x + size.width as i32
+            && position.y < origin.y + size.height as i32
+    }))
+}
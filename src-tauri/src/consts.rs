@@ -1,40 +1,180 @@
-use std::time::Duration;
+use std::{ops::Range, time::Duration};
 
 pub const TARGET_NAME: &str = "destiny2.exe";
 pub const OVERLAY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+pub const COMPACT_OVERLAY_WIDTH: u32 = 360;
+pub const COMPACT_OVERLAY_HEIGHT: u32 = 200;
+pub const MIN_OVERLAY_SCALE: f32 = 0.5;
+pub const MAX_OVERLAY_SCALE: f32 = 3.0;
+/// Windows whose position (and size, if resizable) is restored across launches
+pub const PERSISTED_WINDOWS: [&str; 3] = ["details", "preferences", "profiles"];
 pub const APP_NAME: &str = "threepole";
 pub const APP_VER: &str = env!("CARGO_PKG_VERSION");
 pub fn get_api_key() -> String {
     std::env::var("BUNGIE_API_KEY").unwrap_or_else(|_| "5f193ccb77dd424583b1c19413424e43".to_string())
 }
+// Public application IDs are embedded at build time like the API key, and can be overridden at runtime
+pub fn get_discord_client_id() -> Option<String> {
+    std::env::var("DISCORD_CLIENT_ID")
+        .ok()
+        .or_else(|| option_env!("DISCORD_CLIENT_ID").map(String::from))
+        .filter(|id| !id.is_empty())
+}
+pub fn get_oauth_client_id() -> Option<String> {
+    std::env::var("BUNGIE_OAUTH_CLIENT_ID")
+        .ok()
+        .or_else(|| option_env!("BUNGIE_OAUTH_CLIENT_ID").map(String::from))
+        .filter(|id| !id.is_empty())
+}
+// Never embedded, as anything in a shipped binary can be read back out of it. Releases sign in as
+// a public client, which Bungie gives no refresh token, so a session ends when its access token
+// expires. A secret can still be set at runtime for a confidential client registered for testing.
+pub fn get_oauth_client_secret() -> Option<String> {
+    std::env::var("BUNGIE_OAUTH_CLIENT_SECRET")
+        .ok()
+        .filter(|s| !s.is_empty())
+}
 pub const API_PATH: &str = "https://www.bungie.net/Platform";
+#[cfg(windows)]
 pub const NAMED_PIPE: &str = r"\\.\pipe\threepole-open";
+#[cfg(unix)]
+pub const INSTANCE_SOCKET_NAME: &str = "threepole-open.sock";
 pub const USER_AGENT: &str = concat!("threepole/", env!("CARGO_PKG_VERSION"));
 
 pub const RAID_ACTIVITY_MODE: usize = 4;
 pub const DUNGEON_ACTIVITY_MODE: usize = 82;
 pub const STRIKE_ACTIVITY_MODE: usize = 18;
 pub const LOSTSECTOR_ACTIVITY_MODE: usize = 87;
+pub const NIGHTFALL_ACTIVITY_MODE: usize = 46;
+// AllPvP, which Trials games also carry
+pub const CRUCIBLE_ACTIVITY_MODE: usize = 5;
+pub const TRIALS_ACTIVITY_MODE: usize = 84;
+// The Tower and other social spaces
+pub const SOCIAL_ACTIVITY_MODE: usize = 40;
 
 pub const RAID_ACTIVITY_HASH: usize = 2043403989;
+pub const DUNGEON_ACTIVITY_HASH: usize = 608898761;
 
 // Polling intervals
 pub const POLLER_INTERVAL_SECS: u64 = 5;
 pub const POLLER_HISTORY_CHECK_INTERVAL: usize = 5;
+pub const MIN_POLL_INTERVAL_SECS: u32 = 3;
+pub const GAME_CLOSED_POLL_INTERVAL: Duration = Duration::from_secs(120);
+pub const SERVICE_DISABLED_POLL_INTERVAL: Duration = Duration::from_secs(300);
+// Consecutive failures double the poll interval up to this
+pub const ERROR_BACKOFF_MAX_INTERVAL: Duration = Duration::from_secs(60);
+// Consecutive transient poll failures kept off the UI, which carries on with the last good data
+pub const TRANSIENT_ERROR_GRACE_POLLS: u32 = 3;
 pub const CACHE_STALE_MINUTES: i64 = 5;
 
+// Bumped whenever a field filled in from the PGCR is added, so cached activities from before
+// it get their PGCR fetched again rather than the whole cache being dropped
+pub const PGCR_VERSION: u32 = 1;
+
+// Cache limits
+pub const MAX_CACHED_ACTIVITIES_PER_PROFILE: usize = 5000;
+pub const MAX_CACHED_PROFILES: usize = 10;
+
 // API pagination
-pub const ACTIVITY_HISTORY_PAGE_SIZE: usize = 7;
+// AllPvE, walked back to the start of a character's history
+pub const ACTIVITY_HISTORY_MODE: usize = 7;
+// AllPvP is only kept back to the weekly reset, so it's queried separately and stops there
+pub const PVP_HISTORY_MODE: usize = CRUCIBLE_ACTIVITY_MODE;
+pub const ACTIVITY_HISTORY_PAGE_SIZE: usize = 25;
 
 // Concurrency limits
 pub const ACTIVITY_FETCH_CONCURRENCY: usize = 30;
 pub const ACTIVITY_FETCH_WORKERS: usize = 10;
 pub const ACTIVITY_FETCH_MAX_PAGES: usize = 1250;
 pub const PGCR_FETCH_CONCURRENCY: usize = 75;
-#[cfg(debug_assertions)]
+pub const MAX_ACTIVITY_FETCH_CONCURRENCY: usize = 60;
+pub const MAX_PGCR_FETCH_CONCURRENCY: usize = 150;
+pub const PGCR_CHECKPOINT_INTERVAL: usize = 250;
+pub const PGCR_CHECKPOINT_MAX_AGE: Duration = Duration::from_secs(30);
+pub const PGCR_STREAM_INTERVAL: usize = 100;
+pub const HISTORY_STREAM_CAPACITY: usize = 4;
 pub const PGCR_PROGRESS_INTERVAL: usize = 50;
-#[cfg(debug_assertions)]
+pub const PGCR_RESULT_CHANNEL_CAPACITY: usize = 100;
+pub const PGCR_THROTTLE_STREAK: usize = 3;
 pub const PGCR_ERROR_LOG_LIMIT: usize = 10;
 
+// Rate limiting, Bungie documents 25 requests per second
+pub const API_RATE_LIMIT_PER_SEC: u32 = 25;
+pub const MAX_API_RATE_LIMIT_PER_SEC: u32 = 25;
+
+// Throttle handling
+pub const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+pub const MAX_THROTTLE_WAIT_SECS: u64 = 30;
+pub const THROTTLE_BUFFER_MILLIS: u64 = 250;
+// 503 backoff is scaled by a random factor in this range so concurrent workers don't retry in lockstep
+pub const RETRY_JITTER_RANGE: Range<f64> = 0.5..1.5;
+// Request-level retries for history pages and profile lookups, on top of the HTTP layer's own
+pub const TRANSIENT_RETRY_ATTEMPTS: u32 = 4;
+pub const TRANSIENT_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+// Season start dates, launched at that day's reset. Append new seasons as they're announced,
+// clears after the last entry are counted towards it.
+pub const SEASON_STARTS: &[(u32, &str)] = &[
+    (1, "2017-09-06"),
+    (2, "2017-12-05"),
+    (3, "2018-05-08"),
+    (4, "2018-09-04"),
+    (5, "2018-11-27"),
+    (6, "2019-03-05"),
+    (7, "2019-06-04"),
+    (8, "2019-10-01"),
+    (9, "2019-12-10"),
+    (10, "2020-03-10"),
+    (11, "2020-06-09"),
+    (12, "2020-11-10"),
+    (13, "2021-02-09"),
+    (14, "2021-05-11"),
+    (15, "2021-08-24"),
+    (16, "2022-02-22"),
+    (17, "2022-05-24"),
+    (18, "2022-08-23"),
+    (19, "2022-12-06"),
+    (20, "2023-02-28"),
+    (21, "2023-05-23"),
+    (22, "2023-08-22"),
+    (23, "2023-11-28"),
+    (24, "2024-06-04"),
+    (25, "2024-10-08"),
+    (26, "2025-02-04"),
+    (27, "2025-07-15"),
+];
+// Season key for clears older than the first known season start
+pub const UNKNOWN_SEASON: u32 = 0;
+
+// Clear notifications
+pub const CLEAR_TOAST_BATCH_LIMIT: usize = 3;
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 3;
+pub const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(5);
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Local server
+pub const LOCAL_SERVER_PORT: u16 = 7727;
+pub const LOCAL_SERVER_MAX_REQUEST_BYTES: usize = 8192;
+
+// Logging
+// Takes an EnvFilter directive, e.g. THREEPOLE_LOG=pgcr=debug,info, and overrides the preference
+pub const LOG_ENV_VAR: &str = "THREEPOLE_LOG";
+pub const DEFAULT_LOG_LEVEL: &str = if cfg!(debug_assertions) { "debug" } else { "info" };
+pub const LOG_DIR_NAME: &str = "logs";
+pub const LOG_MAX_FILES: usize = 7;
+
+// Bungie OAuth
+pub const OAUTH_AUTHORIZE_URL: &str = "https://www.bungie.net/en/OAuth/Authorize";
+pub const OAUTH_TOKEN_URL: &str = "https://www.bungie.net/Platform/App/OAuth/Token/";
+// Must match the redirect URL registered for the client, i.e. http://127.0.0.1:7728/callback
+pub const OAUTH_REDIRECT_PORT: u16 = 7728;
+pub const OAUTH_REDIRECT_PATH: &str = "/callback";
+pub const OAUTH_CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+pub const OAUTH_STATE_LENGTH: usize = 32;
+// Refresh a little early so a token doesn't expire mid-request
+pub const OAUTH_REFRESH_MARGIN_SECS: i64 = 60;
+
 // Destiny time constants
 pub const DESTINY_DAILY_RESET_HOUR: u32 = 17;
+// The "month" history range spans four weekly resets
+pub const MONTHLY_RANGE_WEEKS: i64 = 4;
@@ -0,0 +1,182 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::api::{requests::BungieResponseError, ApiError};
+
+const BUNGIE_ACCOUNT_NOT_FOUND: isize = 1601;
+const BUNGIE_PRIVACY_RESTRICTION: isize = 1665;
+const BUNGIE_SYSTEM_DISABLED: isize = 5;
+
+#[derive(Debug, Clone)]
+pub enum AppError {
+    Network(String),
+    Throttled { seconds: isize, message: String },
+    ProfilePrivate,
+    ServiceDisabled,
+    NotFound(String),
+    Bungie { error_code: isize, message: String },
+    Server { status_code: u16, message: String },
+    Internal(String),
+}
+
+impl AppError {
+    fn kind(&self) -> &'static str {
+        match self {
+            AppError::Network(_) => "network",
+            AppError::Throttled { .. } => "throttled",
+            AppError::ProfilePrivate => "profilePrivate",
+            AppError::ServiceDisabled => "serviceDisabled",
+            AppError::NotFound(_) => "notFound",
+            AppError::Bungie { .. } => "bungie",
+            AppError::Server { .. } => "server",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    /// A dropped connection, throttling or an outage page, which usually clears up by the next poll
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AppError::Network(_) | AppError::Throttled { .. } => true,
+            AppError::Server { status_code, .. } => *status_code >= 500,
+            _ => false,
+        }
+    }
+
+    /// Which side failed, so the overlay can tell a dropped connection apart from Bungie having issues
+    fn source(&self) -> &'static str {
+        match self {
+            AppError::Network(_) => "network",
+            AppError::Internal(_) => "app",
+            _ => "bungie",
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Network(message) => write!(f, "Network error: {message}"),
+            AppError::Throttled { seconds, message } => {
+                write!(f, "{message}, throttled! ({seconds}s)")
+            }
+            AppError::ProfilePrivate => f.write_str("Profile is private, sign in to view it"),
+            AppError::ServiceDisabled => f.write_str("Bungie is under maintenance"),
+            AppError::NotFound(message) => f.write_str(message),
+            AppError::Bungie {
+                error_code,
+                message,
+            } => write!(f, "{message} ({error_code})"),
+            AppError::Server {
+                status_code,
+                message,
+            } => write!(f, "{message} (HTTP {status_code})"),
+            AppError::Internal(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for AppError {}
+
+// Serialized as { type, source, message, ... } so the UI can branch on type and still show e.message
+impl Serialize for AppError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("AppError", 4)?;
+        state.serialize_field("type", self.kind())?;
+        state.serialize_field("source", self.source())?;
+        state.serialize_field("message", &self.to_string())?;
+
+        match self {
+            AppError::Throttled { seconds, .. } => state.serialize_field("seconds", seconds)?,
+            AppError::Bungie { error_code, .. } => {
+                state.serialize_field("errorCode", error_code)?
+            }
+            AppError::Server { status_code, .. } => {
+                state.serialize_field("statusCode", status_code)?
+            }
+            _ => (),
+        }
+
+        state.end()
+    }
+}
+
+impl From<BungieResponseError> for AppError {
+    fn from(e: BungieResponseError) -> Self {
+        match e {
+            BungieResponseError::NetworkError(e) => AppError::Network(e.to_string()),
+            BungieResponseError::ServiceUnavailable { retries } => AppError::Server {
+                status_code: 503,
+                message: format!("Bungie API unavailable after {retries} retries"),
+            },
+            BungieResponseError::ResponseMissing => AppError::NotFound(e.to_string()),
+            BungieResponseError::DeserializeError { err, status_code } => AppError::Server {
+                status_code,
+                message: format!("Failed to parse response: {err}"),
+            },
+            BungieResponseError::BungieError {
+                message,
+                error_code,
+                throttle_seconds,
+            } => {
+                if throttle_seconds > 0 {
+                    AppError::Throttled {
+                        seconds: throttle_seconds,
+                        message,
+                    }
+                } else if error_code == BUNGIE_SYSTEM_DISABLED {
+                    AppError::ServiceDisabled
+                } else if error_code == BUNGIE_PRIVACY_RESTRICTION {
+                    AppError::ProfilePrivate
+                } else if error_code == BUNGIE_ACCOUNT_NOT_FOUND {
+                    AppError::NotFound(message)
+                } else {
+                    AppError::Bungie {
+                        error_code,
+                        message,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl From<ApiError> for AppError {
+    fn from(e: ApiError) -> Self {
+        match e {
+            ApiError::ResponseError(e) => e.into(),
+            ApiError::ResponseDeserializeError(_) => AppError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<AppError>() {
+            Ok(e) => return e,
+            Err(e) => e,
+        };
+
+        let e = match e.downcast::<ApiError>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+
+        match e.downcast::<BungieResponseError>() {
+            Ok(e) => e.into(),
+            Err(e) => AppError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
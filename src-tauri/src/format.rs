@@ -0,0 +1,17 @@
+/// Same format as the overlay timer: [h:]mm:ss, with hundredths when enabled
+pub fn format_duration(millis: i64, display_milliseconds: bool) -> String {
+    let millis = millis.max(0);
+    let seconds = millis / 1000;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    let mut formatted = match hours {
+        0 => format!("{minutes:02}:{seconds:02}"),
+        h => format!("{h}:{minutes:02}:{seconds:02}"),
+    };
+
+    if display_milliseconds {
+        formatted.push_str(&format!(":{:02}", millis % 1000 / 10));
+    }
+
+    formatted
+}
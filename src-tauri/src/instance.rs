@@ -0,0 +1,97 @@
+use std::io;
+
+use async_trait::async_trait;
+
+#[cfg(windows)]
+pub use self::pipe::PipeInstanceServer as PlatformInstanceServer;
+
+#[cfg(unix)]
+pub use self::socket::SocketInstanceServer as PlatformInstanceServer;
+
+/// Single-instance endpoint. The first launch claims it; later launches connect to it
+/// so the running instance can bring its window to the front.
+#[async_trait]
+pub trait InstanceServer: Sized + Send {
+    /// Returns `None` after notifying the already running instance.
+    async fn claim() -> io::Result<Option<Self>>;
+
+    /// Waits for another launch to connect.
+    async fn accept(&mut self) -> io::Result<()>;
+}
+
+#[cfg(windows)]
+mod pipe {
+    use std::io;
+
+    use async_trait::async_trait;
+    use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeServer, ServerOptions};
+
+    use super::InstanceServer;
+    use crate::consts::NAMED_PIPE;
+
+    pub struct PipeInstanceServer(NamedPipeServer);
+
+    #[async_trait]
+    impl InstanceServer for PipeInstanceServer {
+        async fn claim() -> io::Result<Option<Self>> {
+            match ServerOptions::new()
+                .first_pipe_instance(true)
+                .create(NAMED_PIPE)
+            {
+                Ok(s) => Ok(Some(Self(s))),
+                Err(_) => {
+                    ClientOptions::new().open(NAMED_PIPE)?;
+                    Ok(None)
+                }
+            }
+        }
+
+        async fn accept(&mut self) -> io::Result<()> {
+            self.0.connect().await?;
+            self.0 = ServerOptions::new().create(NAMED_PIPE)?;
+            self.0.disconnect()
+        }
+    }
+}
+
+#[cfg(unix)]
+mod socket {
+    use std::{io, path::PathBuf};
+
+    use async_trait::async_trait;
+    use tokio::net::{UnixListener, UnixStream};
+
+    use super::InstanceServer;
+    use crate::consts::INSTANCE_SOCKET_NAME;
+
+    pub struct SocketInstanceServer(UnixListener);
+
+    fn socket_path() -> PathBuf {
+        dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join(INSTANCE_SOCKET_NAME)
+    }
+
+    #[async_trait]
+    impl InstanceServer for SocketInstanceServer {
+        async fn claim() -> io::Result<Option<Self>> {
+            let path = socket_path();
+
+            if UnixStream::connect(&path).await.is_ok() {
+                return Ok(None);
+            }
+
+            // Nobody is listening, so whatever is left at the path is from a crashed instance
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => (),
+            }
+
+            Ok(Some(Self(UnixListener::bind(&path)?)))
+        }
+
+        async fn accept(&mut self) -> io::Result<()> {
+            self.0.accept().await.map(|_| ())
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{path::PathBuf, sync::OnceLock};
+
+use directories::BaseDirs;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
+
+use crate::consts::{APP_NAME, DEFAULT_LOG_LEVEL, LOG_DIR_NAME, LOG_ENV_VAR, LOG_MAX_FILES};
+
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// The env var wins over the preference, so logging can be turned up without opening the app.
+/// Invalid directives fall back to the default level.
+fn build_filter(level: Option<&str>) -> EnvFilter {
+    std::env::var(LOG_ENV_VAR)
+        .ok()
+        .or_else(|| level.map(str::to_string))
+        .and_then(|l| EnvFilter::try_new(l).ok())
+        .unwrap_or_else(|| EnvFilter::new(DEFAULT_LOG_LEVEL))
+}
+
+fn get_log_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|d| d.data_dir().join(APP_NAME).join(LOG_DIR_NAME))
+}
+
+/// Logs to a daily rotated file next to the config, and to stdout in debug builds. The returned
+/// guard flushes the file when dropped, so it has to live until the app exits.
+pub fn init(level: Option<&str>) -> Option<WorkerGuard> {
+    let (filter, handle) = reload::Layer::new(build_filter(level));
+    let _ = FILTER_HANDLE.set(handle);
+
+    let appender = get_log_dir().and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(APP_NAME)
+            .filename_suffix("log")
+            .max_log_files(LOG_MAX_FILES)
+            .build(dir)
+            .ok()
+    });
+
+    let (file_layer, guard) = match appender {
+        Some(a) => {
+            let (writer, guard) = tracing_appender::non_blocking(a);
+            (Some(fmt::layer().with_writer(writer).with_ansi(false)), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(cfg!(debug_assertions).then(fmt::layer))
+        .try_init();
+
+    guard
+}
+
+/// Applies a changed `log_level` preference without a restart
+pub fn set_level(level: Option<&str>) {
+    if let Some(handle) = FILTER_HANDLE.get() {
+        let _ = handle.reload(build_filter(level));
+    }
+}
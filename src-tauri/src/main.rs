@@ -3,38 +3,59 @@
     windows_subsystem = "windows"
 )]
 
-use std::io;
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::Path,
+};
 
 use api::{
-    responses::{ActivityInfo, BungieProfile, ProfileInfo},
+    auth, requests,
+    responses::{
+        ActivityInfo, BungieProfile, CareerStats, Character, CompletedActivity,
+        PostGameCarnageReport, ProfileInfo, TrackableActivity,
+    },
     Api, Source,
 };
-use cache::CacheManager;
+use cache::{get_profile_id, CacheManager, CacheStats, DefinitionCache};
 use config::{
     preferences::Preferences,
     profiles::{Profile, Profiles},
+    window_state::{get_window_state, is_on_screen, record_window_state, save_window_states},
     ConfigManager,
 };
-use consts::{APP_NAME, APP_VER, NAMED_PIPE};
+use consts::{
+    APP_NAME, APP_VER, COMPACT_OVERLAY_HEIGHT, COMPACT_OVERLAY_WIDTH, PERSISTED_WINDOWS,
+};
+use error::AppError;
+use instance::{InstanceServer, PlatformInstanceServer};
 use pollers::{
+    discord::DiscordPresence,
     overlay::overlay_poller,
-    playerdata::{PlayerDataPoller, PlayerDataStatus},
+    playerdata::{self, PlayerDataPoller, PlayerDataStatus},
 };
+use server::LocalServer;
 use tauri::{
     async_runtime::{self, JoinHandle},
-    AppHandle, CustomMenuItem, Manager, RunEvent, State, SystemTray, SystemTrayEvent,
-    SystemTrayMenu, SystemTrayMenuItem, WindowBuilder, WindowUrl,
-};
-use tokio::{
-    net::windows::named_pipe::{ClientOptions, NamedPipeServer, ServerOptions},
-    sync::Mutex,
+    AppHandle, ClipboardManager, CustomMenuItem, GlobalShortcutManager, LogicalPosition,
+    LogicalSize, Manager, RunEvent, State, SystemTray, SystemTrayEvent, SystemTrayMenu,
+    SystemTrayMenuItem, Window, WindowBuilder, WindowEvent, WindowUrl,
 };
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
 
 mod api;
 mod cache;
 mod config;
 mod consts;
+mod error;
+mod format;
+mod instance;
+mod logging;
 mod pollers;
+mod server;
+mod sound;
+mod webhook;
 
 struct ConfigContainer(Mutex<ConfigManager>);
 
@@ -46,6 +67,12 @@ struct PlayerDataPollerContainer(Mutex<PlayerDataPoller>);
 #[derive(Default)]
 struct OverlayPollerHandle(Mutex<Option<JoinHandle<()>>>);
 
+#[derive(Default)]
+struct DiscordPresenceContainer(std::sync::Mutex<DiscordPresence>);
+
+#[derive(Default)]
+struct LocalServerContainer(Mutex<Option<LocalServer>>);
+
 #[tauri::command]
 async fn open_preferences(handle: AppHandle) -> Result<(), tauri::Error> {
     open_preferences_window(&handle)
@@ -67,14 +94,59 @@ async fn set_preferences(
     preferences: Preferences,
     container: State<'_, ConfigContainer>,
     poller_handle: State<'_, OverlayPollerHandle>,
-) -> Result<(), String> {
+    presence_container: State<'_, DiscordPresenceContainer>,
+    poller_container: State<'_, PlayerDataPollerContainer>,
+) -> Result<(), AppError> {
     let mut lock = container.0.lock().await;
-    lock.set_preferences(preferences.clone())
-        .map_err(|e| e.to_string())?;
+
+    let old = lock.get_preferences();
+    let history_changed = old.filters != preferences.filters || old.sorting != preferences.sorting;
+    let old_hotkey = old.toggle_overlay_hotkey.clone();
+    let hotkey_changed = old_hotkey != preferences.toggle_overlay_hotkey;
+
+    // Registered before anything is saved, so an accelerator that doesn't parse or is already
+    // taken is rejected with the previous hotkey put back
+    if hotkey_changed {
+        if let Err(e) = register_overlay_hotkey(&handle, preferences.toggle_overlay_hotkey.as_deref()) {
+            restore_overlay_hotkey(&handle, old_hotkey.as_deref());
+            return Err(e.into());
+        }
+    }
+
+    if let Err(e) = lock.set_preferences(preferences) {
+        if hotkey_changed {
+            restore_overlay_hotkey(&handle, old_hotkey.as_deref());
+        }
+
+        return Err(e.into());
+    }
+
+    let preferences = lock.get_preferences().clone();
+    drop(lock);
+
+    sync_local_server(&handle, &preferences).await;
+    logging::set_level(preferences.log_level.as_deref());
+    requests::set_rate_limit(preferences.requests_per_second);
+
+    if history_changed {
+        poller_container.0.lock().await.refresh_history(handle.clone());
+    }
+
+    presence_container
+        .0
+        .lock()
+        .unwrap()
+        .set_enabled(preferences.enable_discord_presence);
 
     if let Some(o) = handle.get_window("overlay") {
         if preferences.enable_overlay {
-            let _ = o.emit("preferences_update", preferences);
+            let _ = place_overlay(
+                &o,
+                preferences.overlay_monitor_index,
+                preferences.compact_overlay,
+                preferences.overlay_scale,
+            );
+            let _ = o.emit("preferences_update", preferences.clone());
         } else {
             if let Some(h) = poller_handle.0.lock().await.as_ref() {
                 h.abort();
@@ -83,12 +155,169 @@ async fn set_preferences(
             let _ = o.close();
         }
     } else if preferences.enable_overlay {
-        let _ = create_overlay(handle).await;
+        let _ = create_overlay(handle.clone(), &preferences).await;
     }
 
     Ok(())
 }
 
+#[tauri::command]
+async fn save_view(
+    handle: AppHandle,
+    name: String,
+    container: State<'_, ConfigContainer>,
+) -> Result<Preferences, AppError> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err(AppError::Internal("View name can't be empty".to_string()));
+    }
+
+    update_views(&handle, &container, |p| {
+        p.save_view(name.to_string());
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn delete_view(
+    handle: AppHandle,
+    name: String,
+    container: State<'_, ConfigContainer>,
+) -> Result<Preferences, AppError> {
+    update_views(&handle, &container, |p| match p.delete_view(&name) {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("No view named {name}"))),
+    })
+    .await
+}
+
+#[tauri::command]
+async fn apply_view(
+    handle: AppHandle,
+    name: String,
+    container: State<'_, ConfigContainer>,
+    poller_container: State<'_, PlayerDataPollerContainer>,
+) -> Result<Preferences, AppError> {
+    let preferences = update_views(&handle, &container, |p| match p.apply_view(&name) {
+        true => Ok(()),
+        false => Err(AppError::NotFound(format!("No view named {name}"))),
+    })
+    .await?;
+
+    poller_container.0.lock().await.refresh_history(handle.clone());
+
+    Ok(preferences)
+}
+
+/// Saves the changed preferences and sends them to every window, so open filter and sort
+/// controls follow along.
+async fn update_views(
+    handle: &AppHandle,
+    container: &ConfigContainer,
+    update: impl FnOnce(&mut Preferences) -> Result<(), AppError>,
+) -> Result<Preferences, AppError> {
+    let mut lock = container.0.lock().await;
+
+    let mut preferences = lock.get_preferences().clone();
+    update(&mut preferences)?;
+    lock.set_preferences(preferences)?;
+
+    let preferences = lock.get_preferences().clone();
+    drop(lock);
+
+    let _ = handle.emit_all("preferences_update", preferences.clone());
+
+    Ok(preferences)
+}
+
+fn register_overlay_hotkey(handle: &AppHandle, hotkey: Option<&str>) -> Result<(), tauri::Error> {
+    let mut shortcut_manager = handle.global_shortcut_manager();
+    shortcut_manager.unregister_all()?;
+
+    if let Some(hotkey) = hotkey.filter(|h| !h.is_empty()) {
+        let handle = handle.clone();
+
+        shortcut_manager.register(hotkey, move || {
+            let handle = handle.clone();
+            async_runtime::spawn(async move { toggle_overlay(handle).await });
+        })?;
+    }
+
+    Ok(())
+}
+
+/// `register_overlay_hotkey` has already unregistered the previous hotkey by the time it fails
+fn restore_overlay_hotkey(handle: &AppHandle, hotkey: Option<&str>) {
+    if let Err(e) = register_overlay_hotkey(handle, hotkey) {
+        warn!(target: "config", "⚠️ Config: Failed to restore the previous overlay hotkey: {}", e);
+    }
+}
+
+/// Shows or hides the overlay for this session without touching the saved preference.
+async fn toggle_overlay(handle: AppHandle) {
+    if let Some(o) = handle.get_window("overlay") {
+        let poller_handle = handle.state::<OverlayPollerHandle>();
+
+        if let Some(h) = poller_handle.0.lock().await.as_ref() {
+            h.abort();
+        }
+
+        let _ = o.close();
+    } else {
+        let preferences = {
+            let config_container = handle.state::<ConfigContainer>();
+            let lock = config_container.0.lock().await;
+            lock.get_preferences().clone()
+        };
+
+        let _ = create_overlay(handle, &preferences).await;
+    }
+}
+
+/// Starts, stops or moves the local server to match the preferences
+async fn sync_local_server(handle: &AppHandle, preferences: &Preferences) {
+    let container = handle.state::<LocalServerContainer>();
+    let mut lock = container.0.lock().await;
+
+    if let Some(server) = lock.as_ref() {
+        if preferences.enable_local_server
+            && server.configured_port == preferences.local_server_port
+        {
+            return;
+        }
+
+        server.stop();
+        *lock = None;
+    }
+
+    if !preferences.enable_local_server {
+        return;
+    }
+
+    let status = handle
+        .state::<PlayerDataPollerContainer>()
+        .0
+        .lock()
+        .await
+        .shared_status();
+
+    match LocalServer::start(status, preferences.local_server_port).await {
+        Ok(server) => *lock = Some(server),
+        Err(e) => {
+            error!(target: "server", "❌ Local server: Failed to start: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_local_server_port(
+    container: State<'_, LocalServerContainer>,
+) -> Result<Option<u16>, ()> {
+    Ok(container.0.lock().await.as_ref().map(|s| s.port))
+}
+
 #[tauri::command]
 async fn get_profiles(container: State<'_, ConfigContainer>) -> Result<Profiles, ()> {
     Ok(container.0.lock().await.get_profiles().clone())
@@ -100,62 +329,353 @@ async fn set_profiles(
     profiles: Profiles,
     config_container: State<'_, ConfigContainer>,
     poller_container: State<'_, PlayerDataPollerContainer>,
-) -> Result<(), String> {
+) -> Result<Profiles, AppError> {
+    let profiles = resolve_cross_save_profiles(profiles).await;
+
+    let new_profiles: Vec<Profile> = {
+        let lock = config_container.0.lock().await;
+        let old = lock.get_profiles();
+
+        profiles
+            .saved_profiles
+            .iter()
+            .chain(profiles.selected_profile.iter())
+            .filter(|p| !old.saved_profiles.contains(p) && old.selected_profile.as_ref() != Some(p))
+            .cloned()
+            .collect()
+    };
+
+    validate_profiles(&handle, &new_profiles).await?;
+
     let mut lock = config_container.0.lock().await;
 
     let was_no_profile = lock.get_profiles().selected_profile.is_none();
 
-    lock.set_profiles(profiles).map_err(|e| e.to_string())?;
+    let removed_profile_ids: HashSet<String> = {
+        let old = lock.get_profiles();
+
+        old.saved_profiles
+            .iter()
+            .chain(old.selected_profile.iter())
+            .filter(|p| {
+                !profiles.saved_profiles.contains(p) && profiles.selected_profile.as_ref() != Some(p)
+            })
+            .map(get_profile_id)
+            .collect()
+    };
+
+    lock.set_profiles(profiles.clone())?;
 
     if was_no_profile {
         if handle.get_window("overlay").is_none() && lock.get_preferences().enable_overlay {
-            let _ = create_overlay(handle.clone()).await;
+            let _ = create_overlay(handle.clone(), lock.get_preferences()).await;
         }
 
         let _ = open_details_window(&handle, true);
     }
 
-    poller_container.0.lock().await.reset(handle).await;
+    drop(lock);
+
+    // Resetting first aborts any fetch that's still holding the cache
+    poller_container.0.lock().await.reset(handle.clone()).await;
+
+    if !removed_profile_ids.is_empty() {
+        let cache_container = handle.state::<CacheContainer>();
+        let mut lock = cache_container.0.lock().await;
+
+        for profile_id in removed_profile_ids.iter() {
+            lock.remove_profile_cache(profile_id);
+        }
+
+        lock.save_in_background();
+    }
+
+    Ok(profiles)
+}
+
+/// Signs in with Bungie.net and selects the account's Destiny profile, saving it if it's new
+#[tauri::command]
+async fn sign_in(
+    handle: AppHandle,
+    config_container: State<'_, ConfigContainer>,
+    poller_container: State<'_, PlayerDataPollerContainer>,
+) -> Result<Profiles, AppError> {
+    let token = auth::sign_in(&handle).await?;
+
+    let profile = Api::get_current_user_profile(&token.access_token)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No Destiny 2 account found".to_string()))?;
+
+    let mut profiles = config_container.0.lock().await.get_profiles().clone();
+
+    if !profiles.saved_profiles.contains(&profile) {
+        profiles.saved_profiles.push(profile.clone());
+    }
+
+    profiles.selected_profile = Some(profile);
+
+    set_profiles(handle, profiles, config_container, poller_container).await
+}
+
+#[tauri::command]
+async fn sign_out(handle: AppHandle) -> Result<(), AppError> {
+    auth::sign_out().await;
+
+    // Drop anything that was only visible while signed in
+    let poller_container = handle.state::<PlayerDataPollerContainer>();
+    poller_container.0.lock().await.reset(handle.clone()).await;
 
     Ok(())
 }
 
 #[tauri::command]
-async fn get_profile_info(profile: Profile, api: State<'_, Api>) -> Result<ProfileInfo, String> {
-    Ok(api
-        .profile_info_source
-        .lock()
-        .await
-        .get(&profile)
-        .await
-        .map_err(|e| e.to_string())?)
+async fn is_signed_in() -> Result<bool, ()> {
+    Ok(auth::is_signed_in().await)
+}
+
+/// Looks up each profile before it's saved, so a bad platform or membership id is rejected up
+/// front with a clear error instead of surfacing later as a poll error. Other lookup failures
+/// (e.g. a private profile or a network error) are passed back as they are.
+async fn validate_profiles(handle: &AppHandle, profiles: &[Profile]) -> Result<(), AppError> {
+    let api = handle.state::<Api>();
+
+    for profile in profiles {
+        match api.profile_info_source.get(profile).await.map_err(AppError::from) {
+            Ok(_) => (),
+            Err(AppError::NotFound(_)) => {
+                return Err(AppError::NotFound(format!(
+                    "Profile {} doesn't exist, check the platform and membership id",
+                    profile.account_id
+                )))
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Swaps each profile for the cross-save primary membership that actually holds its history.
+/// Profiles that can't be resolved (e.g. network errors) are kept as entered.
+async fn resolve_cross_save_profiles(profiles: Profiles) -> Profiles {
+    async fn resolve(profile: Profile) -> Profile {
+        match Api::get_linked_profiles(profile.account_platform, &profile.account_id).await {
+            Ok(Some(primary)) => primary,
+            _ => profile,
+        }
+    }
+
+    let mut saved_profiles = Vec::with_capacity(profiles.saved_profiles.len());
+
+    for profile in profiles.saved_profiles {
+        let profile = resolve(profile).await;
+
+        if !saved_profiles.contains(&profile) {
+            saved_profiles.push(profile);
+        }
+    }
+
+    let selected_profile = match profiles.selected_profile {
+        Some(p) => Some(resolve(p).await),
+        None => None,
+    };
+
+    let mut tracked_profiles = Vec::with_capacity(profiles.tracked_profiles.len());
+
+    for profile in profiles.tracked_profiles {
+        let profile = resolve(profile).await;
+
+        if saved_profiles.contains(&profile) && !tracked_profiles.contains(&profile) {
+            tracked_profiles.push(profile);
+        }
+    }
+
+    Profiles {
+        saved_profiles,
+        selected_profile,
+        tracked_profiles,
+    }
+}
+
+#[tauri::command]
+async fn get_profile_info(profile: Profile, api: State<'_, Api>) -> Result<ProfileInfo, AppError> {
+    Ok(api.profile_info_source.get(&profile).await?)
+}
+
+#[tauri::command]
+async fn resolve_profile(profile: Profile) -> Result<Profile, AppError> {
+    let primary =
+        Api::get_linked_profiles(profile.account_platform, &profile.account_id).await?;
+
+    Ok(primary.unwrap_or(profile))
+}
+
+#[tauri::command]
+async fn get_career_stats(profile: Profile) -> Result<CareerStats, AppError> {
+    Ok(Api::get_career_stats(&profile).await?)
+}
+
+#[tauri::command]
+async fn get_characters(profile: Profile) -> Result<Vec<Character>, AppError> {
+    Ok(Api::get_characters(&profile).await?.0)
 }
 
 #[tauri::command]
 async fn get_activity_info(
     activity_hash: usize,
     api: State<'_, Api>,
-) -> Result<ActivityInfo, String> {
-    Ok(api
-        .activity_info_source
-        .lock()
-        .await
-        .get(&activity_hash)
-        .await
-        .map_err(|e| e.to_string())?)
+) -> Result<ActivityInfo, AppError> {
+    Ok(api.activity_info_source.get(&activity_hash).await?)
+}
+
+#[tauri::command]
+async fn get_activity_details(
+    instance_id: String,
+    api: State<'_, Api>,
+) -> Result<PostGameCarnageReport, AppError> {
+    Ok(api.pgcr_source.get(&instance_id).await?)
+}
+
+#[tauri::command]
+async fn get_trackable_activities(api: State<'_, Api>) -> Result<Vec<TrackableActivity>, ()> {
+    Ok(api.activity_info_source.get_trackable_activities().await)
 }
 
 #[tauri::command]
 async fn search_profile(
     display_name: String,
     display_name_code: usize,
-) -> Result<Vec<BungieProfile>, String> {
-    Ok(Api::search_profile(&display_name, display_name_code)
-        .await
-        .map_err(|e| e.to_string())?)
+) -> Result<Vec<BungieProfile>, AppError> {
+    let profiles = Api::search_profile(&display_name, display_name_code).await?;
+
+    Ok(profiles
+        .into_iter()
+        .filter(|p| p.is_cross_save_primary())
+        .collect())
+}
+
+/// Finds profiles by their old platform handle, for users who don't know their Bungie name
+#[tauri::command]
+async fn search_profile_legacy(
+    display_name: String,
+    platform: isize,
+) -> Result<Vec<BungieProfile>, AppError> {
+    let profiles = Api::search_profile_legacy(&display_name, platform).await?;
+
+    Ok(profiles
+        .into_iter()
+        .filter(|p| p.is_cross_save_primary())
+        .collect())
 }
 
-async fn create_overlay(handle: AppHandle) -> Result<(), tauri::Error> {
+#[tauri::command]
+async fn refresh_cache(handle: AppHandle) -> Result<(), ()> {
+    refresh_player_data(&handle).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn check_now(poller_container: State<'_, PlayerDataPollerContainer>) -> Result<(), ()> {
+    poller_container.0.lock().await.check_now();
+    Ok(())
+}
+
+#[tauri::command]
+async fn cancel_rebuild() -> Result<bool, ()> {
+    Ok(playerdata::cancel_rebuild())
+}
+
+#[tauri::command]
+async fn get_cache_stats(cache_container: State<'_, CacheContainer>) -> Result<CacheStats, ()> {
+    let profiles = cache_container.0.lock().await.get_cache_stats();
+
+    Ok(CacheStats {
+        profiles,
+        disk_size_bytes: CacheManager::get_cache_disk_size().await,
+    })
+}
+
+#[tauri::command]
+async fn clear_all_data(handle: AppHandle) -> Result<(), AppError> {
+    let poller_container = handle.state::<PlayerDataPollerContainer>();
+    let mut poller = poller_container.0.lock().await;
+
+    // Aborting first releases the cache if a rebuild is holding it
+    poller.abort();
+
+    let cleared = {
+        let cache_container = handle.state::<CacheContainer>();
+        let mut lock = cache_container.0.lock().await;
+
+        lock.clear_cache();
+        CacheManager::clear_cache_directory().await
+    };
+
+    poller.reset(handle.clone()).await;
+
+    Ok(cleared?)
+}
+
+#[tauri::command]
+async fn export_cache(
+    path: String,
+    cache_container: State<'_, CacheContainer>,
+) -> Result<(), AppError> {
+    let snapshot = cache_container.0.lock().await.export_snapshot()?;
+
+    Ok(CacheManager::write_export(Path::new(&path), snapshot).await?)
+}
+
+/// Merges a backup into the cache, or swaps the cache for it with `replace`, then reloads
+#[tauri::command]
+async fn import_cache(handle: AppHandle, path: String, replace: bool) -> Result<usize, AppError> {
+    let imported = CacheManager::read_export(Path::new(&path)).await?;
+
+    let poller_container = handle.state::<PlayerDataPollerContainer>();
+    let mut poller = poller_container.0.lock().await;
+
+    // Aborted first, so a rebuild still running can't overwrite the import with what it fetched
+    poller.abort();
+
+    let count = {
+        let cache_container = handle.state::<CacheContainer>();
+        let mut lock = cache_container.0.lock().await;
+
+        let count = lock.import(imported, replace);
+        lock.save_in_background();
+        count
+    };
+
+    poller.reset(handle.clone()).await;
+
+    Ok(count)
+}
+
+async fn refresh_player_data(handle: &AppHandle) {
+    let selected_profile = {
+        let config_container = handle.state::<ConfigContainer>();
+        let lock = config_container.0.lock().await;
+        lock.get_profiles().selected_profile.clone()
+    };
+
+    let poller_container = handle.state::<PlayerDataPollerContainer>();
+    let mut poller = poller_container.0.lock().await;
+
+    // Aborting first releases the cache if a rebuild is holding it
+    poller.abort();
+
+    if let Some(profile) = selected_profile {
+        let cache_container = handle.state::<CacheContainer>();
+        let mut lock = cache_container.0.lock().await;
+
+        lock.remove_profile_cache(&get_profile_id(&profile));
+        lock.save_in_background();
+    }
+
+    poller.reset(handle.clone()).await;
+}
+
+async fn create_overlay(handle: AppHandle, preferences: &Preferences) -> Result<(), tauri::Error> {
     let overlay = WindowBuilder::new(
         &handle,
         "overlay",
@@ -164,13 +684,19 @@ async fn create_overlay(handle: AppHandle) -> Result<(), tauri::Error> {
     .title(APP_NAME)
     .transparent(true)
     .decorations(false)
-    .fullscreen(true)
+    .inner_size(COMPACT_OVERLAY_WIDTH as f64, COMPACT_OVERLAY_HEIGHT as f64)
     .resizable(false)
     .always_on_top(true)
     .visible(false)
     .skip_taskbar(true)
     .build()?;
 
+    place_overlay(
+        &overlay,
+        preferences.overlay_monitor_index,
+        preferences.compact_overlay,
+        preferences.overlay_scale,
+    )?;
     overlay.set_ignore_cursor_events(true)?;
 
     #[cfg(debug_assertions)]
@@ -191,11 +717,84 @@ async fn create_overlay(handle: AppHandle) -> Result<(), tauri::Error> {
     Ok(())
 }
 
+/// Size of the compact overlay, which grows with the widget's CSS `overlay_scale`
+fn compact_overlay_size(overlay_scale: f32) -> LogicalSize<f64> {
+    LogicalSize::new(
+        COMPACT_OVERLAY_WIDTH as f64 * overlay_scale as f64,
+        COMPACT_OVERLAY_HEIGHT as f64 * overlay_scale as f64,
+    )
+}
+
+/// Moves the overlay onto the chosen monitor, covering it unless compact. Falls back to the
+/// primary monitor when that index isn't connected, or when none is chosen and the overlay
+/// poller hasn't moved it onto the game yet.
+fn place_overlay(
+    overlay: &Window,
+    monitor_index: Option<usize>,
+    compact: bool,
+    overlay_scale: f32,
+) -> Result<(), tauri::Error> {
+    let chosen = match monitor_index {
+        Some(i) => overlay.available_monitors()?.into_iter().nth(i),
+        None => None,
+    };
+
+    let monitor = match chosen {
+        Some(m) => Some(m),
+        None => overlay.primary_monitor()?,
+    };
+
+    let Some(monitor) = monitor else {
+        return Ok(());
+    };
+
+    overlay.set_position(*monitor.position())?;
+
+    if compact {
+        overlay.set_size(compact_overlay_size(overlay_scale))
+    } else {
+        overlay.set_size(*monitor.size())
+    }
+}
+
 #[tauri::command]
 async fn get_playerdata(
     poller_container: State<'_, PlayerDataPollerContainer>,
-) -> Result<Option<PlayerDataStatus>, ()> {
-    Ok(poller_container.0.lock().await.get_data())
+) -> Result<PlayerDataStatus, ()> {
+    Ok(poller_container.0.lock().await.get_data().await)
+}
+
+#[tauri::command]
+async fn copy_run_summary(
+    handle: AppHandle,
+    config_container: State<'_, ConfigContainer>,
+    poller_container: State<'_, PlayerDataPollerContainer>,
+) -> Result<String, AppError> {
+    let display_milliseconds = config_container
+        .0
+        .lock()
+        .await
+        .get_preferences()
+        .display_milliseconds;
+
+    let summary = poller_container
+        .0
+        .lock()
+        .await
+        .run_summary(&handle, display_milliseconds)
+        .await
+        .ok_or_else(|| AppError::NotFound("No activity to summarize".to_string()))?;
+
+    handle.clipboard_manager().write_text(summary.clone())?;
+
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn get_personal_bests(
+    poller_container: State<'_, PlayerDataPollerContainer>,
+) -> Result<HashMap<usize, CompletedActivity>, ()> {
+    Ok(poller_container.0.lock().await.get_personal_bests().await)
 }
 
 
@@ -205,7 +804,7 @@ fn open_preferences_window(handle: &AppHandle) -> Result<(), tauri::Error> {
         return w.set_focus();
     }
 
-    WindowBuilder::new(
+    let window = WindowBuilder::new(
         handle,
         "preferences",
         WindowUrl::App("./src/window/window.html#preferences".into()),
@@ -217,7 +816,7 @@ fn open_preferences_window(handle: &AppHandle) -> Result<(), tauri::Error> {
     .visible(false)
     .build()?;
 
-    Ok(())
+    restore_window_state(&window)
 }
 
 fn open_profiles_window(handle: &AppHandle) -> Result<(), tauri::Error> {
@@ -226,7 +825,7 @@ fn open_profiles_window(handle: &AppHandle) -> Result<(), tauri::Error> {
         return w.set_focus();
     }
 
-    WindowBuilder::new(
+    let window = WindowBuilder::new(
         handle,
         "profiles",
         WindowUrl::App("./src/window/window.html#profiles".into()),
@@ -238,7 +837,7 @@ fn open_profiles_window(handle: &AppHandle) -> Result<(), tauri::Error> {
     .visible(false)
     .build()?;
 
-    Ok(())
+    restore_window_state(&window)
 }
 
 fn open_details_window(handle: &AppHandle, welcome: bool) -> Result<(), tauri::Error> {
@@ -247,7 +846,7 @@ fn open_details_window(handle: &AppHandle, welcome: bool) -> Result<(), tauri::E
         return w.set_focus();
     }
 
-    WindowBuilder::new(
+    let window = WindowBuilder::new(
         handle,
         "details",
         WindowUrl::App(
@@ -261,10 +860,33 @@ fn open_details_window(handle: &AppHandle, welcome: bool) -> Result<(), tauri::E
     .title(APP_NAME)
     .decorations(false)
     .inner_size(600.0, 600.0)
-    .resizable(false)
+    .min_inner_size(600.0, 600.0)
+    .resizable(true)
     .visible(false)
     .build()?;
 
+    restore_window_state(&window)
+}
+
+/// Puts a freshly built window back where it was last left, unless that monitor is gone.
+/// Only resizable windows get their size back, the rest keep their fixed layout.
+fn restore_window_state(window: &Window) -> Result<(), tauri::Error> {
+    let Some(state) = get_window_state(window.label()) else {
+        return Ok(());
+    };
+
+    let position = LogicalPosition::new(state.x, state.y);
+
+    if !is_on_screen(window, position.to_physical(window.scale_factor()?))? {
+        return Ok(());
+    }
+
+    window.set_position(position)?;
+
+    if window.is_resizable()? {
+        window.set_size(LogicalSize::new(state.width, state.height))?;
+    }
+
     Ok(())
 }
 
@@ -279,11 +901,9 @@ async fn activate(handle: &AppHandle) -> Result<(), tauri::Error> {
     }
 }
 
-async fn pipe_loop(handle: AppHandle, mut pipe_server: NamedPipeServer) -> io::Result<()> {
+async fn pipe_loop(handle: AppHandle, mut pipe_server: impl InstanceServer) -> io::Result<()> {
     loop {
-        pipe_server.connect().await?;
-        pipe_server = ServerOptions::new().create(NAMED_PIPE)?;
-        pipe_server.disconnect()?;
+        pipe_server.accept().await?;
 
         let _ = activate(&handle).await;
     }
@@ -293,39 +913,44 @@ async fn pipe_loop(handle: AppHandle, mut pipe_server: NamedPipeServer) -> io::R
 async fn main() -> anyhow::Result<()> {
     dotenv::dotenv().ok();
     
-    let pipe_server = match ServerOptions::new()
-        .first_pipe_instance(true)
-        .create(NAMED_PIPE)
-    {
-        Ok(s) => s,
-        Err(_) => {
-            ClientOptions::new().open(NAMED_PIPE)?;
-            return Ok(());
-        }
+    let pipe_server = match PlatformInstanceServer::claim().await? {
+        Some(s) => s,
+        None => return Ok(()),
     };
 
     tauri::async_runtime::set(tokio::runtime::Handle::current());
 
+    let config_manager = ConfigManager::load()?;
+    let _log_guard = logging::init(config_manager.get_preferences().log_level.as_deref());
+    requests::set_rate_limit(config_manager.get_preferences().requests_per_second);
 
     let cache_manager = match CacheManager::load().await {
         Ok(cache) => {
-            #[cfg(debug_assertions)]
-            println!("✅ Cache: Successfully loaded cache manager");
+            info!(target: "cache", "✅ Cache: Successfully loaded cache manager");
             cache
         },
-        Err(_e) => {
-            #[cfg(debug_assertions)]
-            println!("⚠️ Cache: Failed to load cache, creating new: {}", _e);
+        Err(e) => {
+            warn!(target: "cache", "⚠️ Cache: Failed to load cache, creating new: {}", e);
             CacheManager::new()
         }
     };
+
+    let definition_cache = match DefinitionCache::load().await {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            warn!(target: "definitions", "⚠️ Definitions: Failed to load definition cache, creating new: {}", e);
+            DefinitionCache::default()
+        }
+    };
     
     tauri::Builder::new()
-        .manage(ConfigContainer(Mutex::new(ConfigManager::load()?)))
+        .manage(ConfigContainer(Mutex::new(config_manager)))
         .manage(CacheContainer(Mutex::new(cache_manager)))
-        .manage(Api::default())
+        .manage(Api::new(definition_cache))
         .manage(PlayerDataPollerContainer::default())
         .manage(OverlayPollerHandle::default())
+        .manage(DiscordPresenceContainer::default())
+        .manage(LocalServerContainer::default())
         .system_tray(
             SystemTray::new().with_menu(
                 SystemTrayMenu::new()
@@ -333,19 +958,48 @@ async fn main() -> anyhow::Result<()> {
                         CustomMenuItem::new("version_info", format!("{APP_NAME} v{}", APP_VER))
                             .disabled(),
                     )
+                    .add_item(CustomMenuItem::new("daily_clears", "Clears today: -").disabled())
                     .add_native_item(SystemTrayMenuItem::Separator)
                     .add_item(CustomMenuItem::new("preferences", "Preferences"))
                     .add_item(CustomMenuItem::new("set_profile", "Set profile"))
+                    .add_item(CustomMenuItem::new("refresh_data", "Refresh data"))
                     .add_native_item(SystemTrayMenuItem::Separator)
                     .add_item(CustomMenuItem::new("exit", "Exit")),
             ),
         )
+        .on_window_event(|event| {
+            let window = event.window();
+
+            if !PERSISTED_WINDOWS.contains(&window.label()) {
+                return;
+            }
+
+            // Kept in memory while dragging, written once the window closes
+            match event.event() {
+                WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+                    let _ = record_window_state(window);
+                }
+                WindowEvent::Destroyed => {
+                    if let Err(e) = save_window_states() {
+                        warn!(target: "config", "⚠️ Config: Failed to save window positions: {}", e);
+                    }
+                }
+                _ => (),
+            }
+        })
         .on_system_tray_event(|handle, event| {
             if let SystemTrayEvent::MenuItemClick { id, .. } = event {
                 match id.as_str() {
-                    "exit" => handle.exit(0),
+                    "exit" => {
+                        let _ = save_window_states();
+                        handle.exit(0);
+                    }
                     "set_profile" => { let _ = open_profiles_window(&handle); }
                     "preferences" => { let _ = open_preferences_window(&handle); }
+                    "refresh_data" => {
+                        let handle_clone = handle.clone();
+                        async_runtime::spawn(async move { refresh_player_data(&handle_clone).await });
+                    }
                     _ => (),
                 }
             } else if let SystemTrayEvent::LeftClick { .. } = event {
@@ -358,12 +1012,34 @@ async fn main() -> anyhow::Result<()> {
             open_profiles,
             get_preferences,
             set_preferences,
+            save_view,
+            delete_view,
+            apply_view,
             get_profiles,
             set_profiles,
+            sign_in,
+            sign_out,
+            is_signed_in,
             get_profile_info,
+            resolve_profile,
+            get_career_stats,
+            get_characters,
             get_activity_info,
+            get_activity_details,
+            get_trackable_activities,
             search_profile,
+            search_profile_legacy,
             get_playerdata,
+            get_personal_bests,
+            copy_run_summary,
+            refresh_cache,
+            check_now,
+            cancel_rebuild,
+            get_cache_stats,
+            get_local_server_port,
+            clear_all_data,
+            export_cache,
+            import_cache,
         ])
         .setup(|app| {
             let handle = app.handle();
@@ -371,15 +1047,36 @@ async fn main() -> anyhow::Result<()> {
 
             async_runtime::spawn(async move { pipe_loop(pipe_handle, pipe_server).await });
 
+            let manifest_handle = handle.clone();
+
+            async_runtime::spawn(async move {
+                let api = manifest_handle.state::<Api>();
+                let _ = api.activity_info_source.sync_manifest_version().await;
+            });
+
             async_runtime::spawn(async move {
                 let config_container = handle.state::<ConfigContainer>();
                 let lock = config_container.0.lock().await;
 
+                let _ = register_overlay_hotkey(
+                    &handle,
+                    lock.get_preferences().toggle_overlay_hotkey.as_deref(),
+                );
+
+                handle
+                    .state::<DiscordPresenceContainer>()
+                    .0
+                    .lock()
+                    .unwrap()
+                    .set_enabled(lock.get_preferences().enable_discord_presence);
+
+                sync_local_server(&handle, lock.get_preferences()).await;
+
                 if lock.get_profiles().selected_profile.is_none() {
                     let _ = open_profiles_window(&handle);
                 } else {
                     if lock.get_preferences().enable_overlay {
-                        let _ = create_overlay(handle.clone()).await;
+                        let _ = create_overlay(handle.clone(), lock.get_preferences()).await;
                     }
 
                     let _ = open_details_window(&handle, false);
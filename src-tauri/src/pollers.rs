@@ -1,2 +1,5 @@
+pub mod discord;
+// The overlay tracks the game window through Win32, so other platforms get a no-op poller
+#[cfg_attr(not(windows), path = "pollers/overlay_unsupported.rs")]
 pub mod overlay;
 pub mod playerdata;
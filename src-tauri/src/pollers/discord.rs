@@ -0,0 +1,99 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use chrono::{DateTime, Utc};
+use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
+use tauri::async_runtime;
+
+use crate::consts::{get_discord_client_id, APP_NAME};
+
+/// Activity hash, name and start of what to show, `None` to clear the presence
+type Presence = Option<(usize, String, DateTime<Utc>)>;
+
+/// Hands presence changes to a blocking task that owns the Discord connection, so callers never
+/// wait on IPC. The task runs while presence is enabled.
+#[derive(Default)]
+pub struct DiscordPresence {
+    updates: Option<Sender<Presence>>,
+}
+
+impl DiscordPresence {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        match (enabled, self.updates.is_some()) {
+            (true, false) => {
+                let (tx, rx) = mpsc::channel();
+                self.updates = Some(tx);
+
+                async_runtime::spawn_blocking(move || run(rx));
+            }
+            // Closing the channel ends the task, which clears the presence on its way out
+            (false, true) => self.updates = None,
+            _ => (),
+        }
+    }
+
+    /// Shows the given activity, or clears the presence when `None`
+    pub fn update(&mut self, current: Option<(usize, &str, DateTime<Utc>)>) {
+        if let Some(updates) = self.updates.as_ref() {
+            let _ = updates.send(current.map(|(hash, name, start)| (hash, name.to_string(), start)));
+        }
+    }
+}
+
+/// Connects once and reuses the connection for every change. Failures are silently dropped
+/// (e.g. Discord not running) and the connection is retried on the next change.
+fn run(updates: Receiver<Presence>) {
+    let mut client: Option<DiscordIpcClient> = None;
+    let mut shown_activity = None;
+
+    while let Ok(mut current) = updates.recv() {
+        // Only the latest change matters if several queued up while Discord was slow
+        while let Ok(newer) = updates.try_recv() {
+            current = newer;
+        }
+
+        let key = current.as_ref().map(|(hash, _, start)| (*hash, *start));
+
+        if client.is_some() && shown_activity == key {
+            continue;
+        }
+
+        if client.is_none() {
+            client = connect();
+        }
+
+        let c = match client.as_mut() {
+            Some(c) => c,
+            None => continue,
+        };
+
+        let res = match &current {
+            Some((_, name, start)) => c.set_activity(
+                activity::Activity::new()
+                    .details(name)
+                    .state(APP_NAME)
+                    .timestamps(activity::Timestamps::new().start(start.timestamp())),
+            ),
+            None => c.clear_activity(),
+        };
+
+        match res {
+            Ok(_) => shown_activity = key,
+            Err(_) => {
+                client = None;
+                shown_activity = None;
+            }
+        }
+    }
+
+    if let Some(mut c) = client {
+        let _ = c.clear_activity();
+        let _ = c.close();
+    }
+}
+
+fn connect() -> Option<DiscordIpcClient> {
+    let mut client = DiscordIpcClient::new(&get_discord_client_id()?).ok()?;
+    client.connect().ok()?;
+
+    Some(client)
+}
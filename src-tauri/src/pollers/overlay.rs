@@ -4,9 +4,9 @@ use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize};
 use widestring::Utf16String;
 use windows::Win32::{
-    Foundation::{HWND, MAX_PATH, RECT},
+    Foundation::{CloseHandle, HWND, MAX_PATH, RECT},
     System::{
-        ProcessStatus::K32GetModuleFileNameExW,
+        ProcessStatus::{K32EnumProcesses, K32GetModuleFileNameExW},
         Threading::{OpenProcess, PROCESS_QUERY_INFORMATION},
     },
     UI::{
@@ -15,7 +15,12 @@ use windows::Win32::{
     },
 };
 
-use crate::consts::{OVERLAY_POLL_INTERVAL, TARGET_NAME};
+use crate::{
+    config::preferences::OverlayPosition,
+    compact_overlay_size,
+    consts::{OVERLAY_POLL_INTERVAL, TARGET_NAME},
+    ConfigContainer,
+};
 
 enum PollResult {
     Open(HWND),
@@ -77,6 +82,10 @@ fn get_hwnd_exec(hwnd: HWND) -> Option<String> {
 
     unsafe { GetWindowThreadProcessId(hwnd, Some(&mut process_id)) };
 
+    get_process_exec(process_id)
+}
+
+fn get_process_exec(process_id: u32) -> Option<String> {
     if process_id == 0 {
         return None;
     }
@@ -91,6 +100,7 @@ fn get_hwnd_exec(hwnd: HWND) -> Option<String> {
     let mut buf: [u16; MAX_PATH as usize] = [0; MAX_PATH as usize];
 
     unsafe { K32GetModuleFileNameExW(h, None, &mut buf) };
+    unsafe { CloseHandle(h) };
 
     let mut path_string = Utf16String::from_slice_lossy(&buf).to_string();
     path_string.retain(|c| c != '\0');
@@ -100,6 +110,77 @@ fn get_hwnd_exec(hwnd: HWND) -> Option<String> {
     return path.file_name().map(|s| s.to_string_lossy().into_owned());
 }
 
+pub fn is_target_running() -> bool {
+    let mut process_ids: [u32; 4096] = [0; 4096];
+    let mut bytes_needed = 0;
+
+    let ok = unsafe {
+        K32EnumProcesses(
+            process_ids.as_mut_ptr(),
+            std::mem::size_of_val(&process_ids) as u32,
+            &mut bytes_needed,
+        )
+    };
+
+    // Assume the game is running if we can't tell, so polling never stalls by mistake
+    if !ok.as_bool() {
+        return true;
+    }
+
+    let count = bytes_needed as usize / std::mem::size_of::<u32>();
+
+    process_ids[..count]
+        .iter()
+        .any(|id| get_process_exec(*id).map_or(false, |n| n.eq_ignore_ascii_case(TARGET_NAME)))
+}
+
+/// Covers the whole game window, or just the anchored corner of it when the overlay is compact.
+/// A `pinned` monitor is covered instead, for when the overlay lives on another screen than the
+/// game. Both are in physical pixels, so the compact size is converted with `scale_factor`.
+fn overlay_placement(
+    game: RECT,
+    pinned: Option<RECT>,
+    position: OverlayPosition,
+    compact: bool,
+    scale: f32,
+    scale_factor: f64,
+) -> (PhysicalPosition<i32>, PhysicalSize<u32>) {
+    let area = pinned.unwrap_or(game);
+
+    if !compact {
+        return (
+            PhysicalPosition {
+                x: area.left,
+                y: area.top,
+            },
+            PhysicalSize {
+                width: (area.right - area.left) as u32,
+                height: (area.bottom - area.top) as u32,
+            },
+        );
+    }
+
+    // Same size as `place_overlay` gives it, in physical pixels
+    let size = compact_overlay_size(scale).to_physical::<i32>(scale_factor);
+    let (width, height) = (size.width, size.height);
+
+    let (x, y) = match position {
+        OverlayPosition::TopLeft => (area.left, area.top),
+        OverlayPosition::TopRight => (area.right - width, area.top),
+        OverlayPosition::BottomLeft => (area.left, area.bottom - height),
+        OverlayPosition::BottomRight => (area.right - width, area.bottom - height),
+        OverlayPosition::Custom { x, y } => (area.left + x, area.top + y),
+    };
+
+    (
+        PhysicalPosition { x, y },
+        PhysicalSize {
+            width: width as u32,
+            height: height as u32,
+        },
+    )
+}
+
 pub async fn overlay_poller(handle: AppHandle) {
     let mut poller = Poller::default();
 
@@ -129,15 +210,36 @@ pub async fn overlay_poller(handle: AppHandle) {
 
                 unsafe { GetWindowRect(hwnd, &mut dims) };
 
-                let _ = overlay.set_position(PhysicalPosition {
-                    x: dims.left,
-                    y: dims.top,
-                });
-
-                let _ = overlay.set_size(PhysicalSize {
-                    width: (dims.right - dims.left) as u32,
-                    height: (dims.bottom - dims.top) as u32,
-                });
+                let (monitor_index, position, compact, scale) = {
+                    let container = handle.state::<ConfigContainer>();
+                    let lock = container.0.lock().await;
+                    let preferences = lock.get_preferences();
+
+                    (
+                        preferences.overlay_monitor_index,
+                        preferences.overlay_position,
+                        preferences.compact_overlay,
+                        preferences.overlay_scale,
+                    )
+                };
+
+                // A monitor that isn't connected any more falls back to following the game
+                let pinned = monitor_index
+                    .and_then(|i| overlay.available_monitors().ok()?.into_iter().nth(i))
+                    .map(|m| RECT {
+                        left: m.position().x,
+                        top: m.position().y,
+                        right: m.position().x + m.size().width as i32,
+                        bottom: m.position().y + m.size().height as i32,
+                    });
+
+                // Of the monitor the overlay is on, which a previous poll already moved it onto
+                let scale_factor = overlay.scale_factor().unwrap_or(1.0);
+                let (position, size) =
+                    overlay_placement(dims, pinned, position, compact, scale, scale_factor);
+
+                let _ = overlay.set_position(position);
+                let _ = overlay.set_size(size);
 
                 let _ = overlay.emit("show", ());
             }
@@ -0,0 +1,7 @@
+use tauri::AppHandle;
+
+pub fn is_target_running() -> bool {
+    true
+}
+
+pub async fn overlay_poller(_handle: AppHandle) {}
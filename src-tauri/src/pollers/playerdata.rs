@@ -1,69 +1,449 @@
-use std::{collections::HashSet, sync::{Arc, LazyLock}, time::Duration};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, LazyLock,
+    },
+    time::{Duration, Instant},
+};
 
-use anyhow::{anyhow, bail, Result};
-use chrono::{DateTime, Utc, Datelike};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc, Weekday};
 use serde::Serialize;
 use tauri::{
+    api::notification::Notification,
     async_runtime::{self, JoinHandle},
     AppHandle, Manager,
 };
-use tokio::sync::Mutex;
+use tokio::{
+    sync::{mpsc, Mutex, Notify, Semaphore, SemaphorePermit},
+    task::JoinSet,
+};
+use tracing::{debug, info, warn};
 
 use crate::{
     api::{
         requests::BungieResponseError,
-        responses::{ActivityInfo, CompletedActivity, LatestCharacterActivity, ProfileInfo},
-        Api, ApiError, Source,
+        responses::{
+            ActivityInfo, ActivityPhase, CompletedActivity, FireteamMember, LatestCharacterActivity,
+            ProfileInfo,
+        },
+        retry_transient, Api, ApiError, Source,
+    },
+    cache::{dedup_activities, get_profile_id, ActivityCache},
+    config::{
+        preferences::{FilterPreferences, SortKey, SortOrder, SortPreferences, TimeRange},
+        profiles::Profile,
     },
-    config::profiles::Profile,
     consts::{
         DUNGEON_ACTIVITY_MODE, RAID_ACTIVITY_MODE, STRIKE_ACTIVITY_MODE, LOSTSECTOR_ACTIVITY_MODE,
-        POLLER_INTERVAL_SECS, POLLER_HISTORY_CHECK_INTERVAL, CACHE_STALE_MINUTES,
-        ACTIVITY_HISTORY_PAGE_SIZE, ACTIVITY_FETCH_CONCURRENCY, ACTIVITY_FETCH_WORKERS,
-        ACTIVITY_FETCH_MAX_PAGES, PGCR_FETCH_CONCURRENCY,
-        DESTINY_DAILY_RESET_HOUR,
+        NIGHTFALL_ACTIVITY_MODE, CRUCIBLE_ACTIVITY_MODE, TRIALS_ACTIVITY_MODE, SOCIAL_ACTIVITY_MODE,
+        CACHE_STALE_MINUTES, GAME_CLOSED_POLL_INTERVAL, SERVICE_DISABLED_POLL_INTERVAL, OVERLAY_POLL_INTERVAL,
+        ERROR_BACKOFF_MAX_INTERVAL, TRANSIENT_ERROR_GRACE_POLLS,
+        ACTIVITY_HISTORY_MODE, PVP_HISTORY_MODE, ACTIVITY_HISTORY_PAGE_SIZE, ACTIVITY_FETCH_WORKERS,
+        ACTIVITY_FETCH_MAX_PAGES, PGCR_CHECKPOINT_INTERVAL,
+        PGCR_CHECKPOINT_MAX_AGE, PGCR_STREAM_INTERVAL, HISTORY_STREAM_CAPACITY,
+        PGCR_PROGRESS_INTERVAL, PGCR_RESULT_CHANNEL_CAPACITY, PGCR_THROTTLE_STREAK,
+        CLEAR_TOAST_BATCH_LIMIT,
+        DESTINY_DAILY_RESET_HOUR, MONTHLY_RANGE_WEEKS, SEASON_STARTS, UNKNOWN_SEASON,
     },
-    ConfigContainer, CacheContainer,
+    error::AppError,
+    format::format_duration,
+    pollers::overlay::is_target_running,
+    sound,
+    webhook::{self, ClearPayload},
+    ConfigContainer, CacheContainer, DiscordPresenceContainer,
 };
 
-static KNOWN_RAID_HASHES: LazyLock<HashSet<usize>> = LazyLock::new(|| {
-    HashSet::from([
-        2122313384, 3213556450, 2693136600, 1042180643, 910380154,
-        3881495763, 1441982566, 1374392663, 2381413764, 107319834,
-        1541433876, 1044919065, 3817322389,
-    ])
-});
+// The clear count last shown in the tray, so unchanged updates can skip it
+static TRAY_DAILY_CLEARS: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+// This week's clear times of each other tracked profile, from its last successful fetch
+static TRACKED_CLEAR_TIMES: LazyLock<std::sync::Mutex<HashMap<String, Vec<DateTime<Utc>>>>> =
+    LazyLock::new(Default::default);
+
+// Only one rebuild runs at a time, as only the poller task starts one
+static REBUILD_RUNNING: AtomicBool = AtomicBool::new(false);
+static REBUILD_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the running rebuild to stop after its in-flight requests, keeping what it has so far.
+/// Returns false when there's no rebuild to cancel.
+pub fn cancel_rebuild() -> bool {
+    if !REBUILD_RUNNING.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    REBUILD_CANCELLED.store(true, Ordering::Relaxed);
+    true
+}
+
+fn is_rebuild_cancelled() -> bool {
+    REBUILD_CANCELLED.load(Ordering::Relaxed)
+}
+
+/// Marks a rebuild as running until dropped, including when the poller task is aborted
+struct RebuildGuard;
 
-static KNOWN_DUNGEON_HASHES: LazyLock<HashSet<usize>> = LazyLock::new(|| {
-    HashSet::from([
-        2032534090, 2823159265, 2582501063, 4078656646, 1077850348,
-        1262462921, 313828469, 300092127, 3834447244, 2727361621,
-    ])
-});
+impl RebuildGuard {
+    fn start() -> Self {
+        REBUILD_CANCELLED.store(false, Ordering::Relaxed);
+        REBUILD_RUNNING.store(true, Ordering::Relaxed);
 
-fn is_known_raid_hash(activity_hash: usize) -> bool {
-    KNOWN_RAID_HASHES.contains(&activity_hash)
+        Self
+    }
 }
 
-fn is_known_dungeon_hash(activity_hash: usize) -> bool {
-    KNOWN_DUNGEON_HASHES.contains(&activity_hash)
+impl Drop for RebuildGuard {
+    fn drop(&mut self) {
+        REBUILD_RUNNING.store(false, Ordering::Relaxed);
+        REBUILD_CANCELLED.store(false, Ordering::Relaxed);
+    }
 }
 
-fn should_keep_activity(activity: &CompletedActivity, weekly_reset: DateTime<Utc>) -> bool {
-    let is_raid_or_dungeon = activity.modes.iter().any(|m| *m == RAID_ACTIVITY_MODE)
+/// Falls back to the modes Bungie reported in the history when the manifest didn't classify it
+fn is_raid_or_dungeon(activity: &CompletedActivity, raid_dungeon_hashes: &HashSet<usize>) -> bool {
+    raid_dungeon_hashes.contains(&activity.activity_hash)
+        || activity.modes.iter().any(|m| *m == RAID_ACTIVITY_MODE)
         || activity.modes.iter().any(|m| *m == DUNGEON_ACTIVITY_MODE)
-        || is_known_raid_hash(activity.activity_hash)
-        || is_known_dungeon_hash(activity.activity_hash);
+}
+
+/// Whether a walk over pruned history can stop at `page`: a full page from before the weekly
+/// reset only holds activities that get pruned. Only used for the PvP walk, never the PvE one, as
+/// raids and dungeons are kept forever. A page holding one (by history modes, since definitions
+/// aren't fetched yet) never counts.
+fn is_past_retention(page: &[CompletedActivity], weekly_reset: DateTime<Utc>) -> bool {
+    page.len() >= ACTIVITY_HISTORY_PAGE_SIZE
+        && page
+            .iter()
+            .all(|a| a.period < weekly_reset && !is_raid_or_dungeon(a, &HashSet::new()))
+}
+
+/// Whether a walk can stop at `page` under the history retention cutoff. History is newest first,
+/// so once a page reaches past the cutoff everything after it is pruned, raids and dungeons too.
+fn is_past_cutoff(page: &[CompletedActivity], cutoff: DateTime<Utc>) -> bool {
+    page.iter().any(|a| a.period < cutoff)
+}
+
+/// Hashes of the given activities that the manifest definition classifies as a raid or dungeon.
+/// Definitions are persisted, so this only hits the network for activities not seen before.
+async fn fetch_raid_dungeon_hashes(
+    handle: &AppHandle,
+    activities: &[CompletedActivity],
+) -> HashSet<usize> {
+    let hashes: HashSet<usize> = activities.iter().map(|a| a.activity_hash).collect();
+    let mut lookups = JoinSet::new();
+
+    for hash in hashes {
+        let handle = handle.clone();
+
+        lookups.spawn(async move {
+            let api = handle.state::<Api>();
+
+            match api.activity_info_source.get(&hash).await {
+                Ok(info) if info.is_raid() || info.is_dungeon() => Some(hash),
+                _ => None,
+            }
+        });
+    }
+
+    let mut raid_dungeon_hashes = HashSet::new();
+
+    while let Some(res) = lookups.join_next().await {
+        if let Ok(Some(hash)) = res {
+            raid_dungeon_hashes.insert(hash);
+        }
+    }
 
-    if is_raid_or_dungeon {
+    raid_dungeon_hashes
+}
+
+fn should_keep_activity(
+    activity: &CompletedActivity,
+    weekly_reset: DateTime<Utc>,
+    raid_dungeon_hashes: &HashSet<usize>,
+) -> bool {
+    if is_raid_or_dungeon(activity, raid_dungeon_hashes) {
         return true;
     }
 
-    let is_strike_or_lost_sector = activity.modes.iter().any(|m| {
-        *m == STRIKE_ACTIVITY_MODE || *m == LOSTSECTOR_ACTIVITY_MODE
+    let is_weekly_activity = activity.modes.iter().any(|m| {
+        *m == STRIKE_ACTIVITY_MODE
+            || *m == LOSTSECTOR_ACTIVITY_MODE
+            || *m == NIGHTFALL_ACTIVITY_MODE
+            || *m == CRUCIBLE_ACTIVITY_MODE
+            || *m == TRIALS_ACTIVITY_MODE
     });
 
-    is_strike_or_lost_sector && activity.period >= weekly_reset
+    is_weekly_activity && activity.period >= weekly_reset
+}
+
+/// Reissues of an activity are keyed by the lowest hash in their family
+fn family_hash(families: &HashMap<usize, usize>, activity_hash: usize) -> usize {
+    families.get(&activity_hash).copied().unwrap_or(activity_hash)
+}
+
+/// Fastest completed fresh-start run of each activity family, keyed by its family hash
+fn compute_personal_bests(
+    activities: &[CompletedActivity],
+    families: &HashMap<usize, usize>,
+) -> HashMap<usize, CompletedActivity> {
+    let mut bests: HashMap<usize, CompletedActivity> = HashMap::new();
+
+    for activity in activities {
+        if !activity.completed || !activity.is_fresh() {
+            continue;
+        }
+
+        let key = family_hash(families, activity.activity_hash);
+
+        match bests.get(&key) {
+            Some(best) if best.activity_duration_seconds <= activity.activity_duration_seconds => (),
+            _ => {
+                bests.insert(key, activity.clone());
+            }
+        }
+    }
+
+    bests
+}
+
+/// Durations of completed fresh-start runs of each activity family, keyed by its family hash
+fn compute_clear_stats(
+    activities: &[CompletedActivity],
+    families: &HashMap<usize, usize>,
+) -> HashMap<usize, ClearStats> {
+    let mut durations: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for activity in activities {
+        if !activity.completed || !activity.is_fresh() {
+            continue;
+        }
+
+        durations
+            .entry(family_hash(families, activity.activity_hash))
+            .or_default()
+            .push(activity.activity_duration_seconds);
+    }
+
+    durations
+        .into_iter()
+        .filter_map(|(hash, d)| ClearStats::from_durations(d).map(|stats| (hash, stats)))
+        .collect()
+}
+
+fn compute_history_stats(
+    activities: &[CompletedActivity],
+    now: DateTime<Utc>,
+    raid_dungeon_hashes: &HashSet<usize>,
+    families: &HashMap<usize, usize>,
+) -> HistoryStats {
+    let daily_reset = get_destiny_daily_reset_time(now);
+    let weekly_reset = get_destiny_weekly_reset_time(now);
+
+    let clears = activities
+        .iter()
+        .filter(|a| a.completed && is_raid_or_dungeon(a, raid_dungeon_hashes));
+
+    let daily_clears = clears.clone().filter(|a| a.period >= daily_reset).count();
+    let weekly_clears = clears.clone().filter(|a| a.period >= weekly_reset).count();
+    let (current_streak, best_streak) = compute_streaks(clears.clone().map(|a| a.period), now);
+    let season_clears = compute_season_clears(clears.map(|a| a.period));
+
+    HistoryStats {
+        personal_bests: compute_personal_bests(activities, families),
+        clear_stats: compute_clear_stats(activities, families),
+        daily_clears,
+        weekly_clears,
+        tracked_daily_clears: daily_clears,
+        tracked_weekly_clears: weekly_clears,
+        next_daily_reset: get_destiny_next_daily_reset_time(now),
+        next_weekly_reset: get_destiny_next_weekly_reset_time(now),
+        current_streak,
+        best_streak,
+        season_clears,
+        current_season_start: current_season_start(now),
+    }
+}
+
+/// Each known season's number and start, oldest first
+fn season_starts() -> Vec<(u32, DateTime<Utc>)> {
+    SEASON_STARTS
+        .iter()
+        .filter_map(|(season, date)| {
+            let start = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(DESTINY_DAILY_RESET_HOUR, 0, 0)?;
+
+            Some((*season, Utc.from_utc_datetime(&start)))
+        })
+        .collect()
+}
+
+/// Start of the latest known season that has begun by `now`
+fn current_season_start(now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    season_starts()
+        .into_iter()
+        .rev()
+        .map(|(_, start)| start)
+        .find(|start| now >= *start)
+}
+
+/// Clears per season number, with clears before the first known season under `UNKNOWN_SEASON`
+fn compute_season_clears(clear_times: impl Iterator<Item = DateTime<Utc>>) -> HashMap<u32, u32> {
+    let starts = season_starts();
+    let mut season_clears: HashMap<u32, u32> = HashMap::new();
+
+    for time in clear_times {
+        let season = starts
+            .iter()
+            .rev()
+            .find(|(_, start)| time >= *start)
+            .map(|(season, _)| *season)
+            .unwrap_or(UNKNOWN_SEASON);
+
+        *season_clears.entry(season).or_default() += 1;
+    }
+
+    season_clears
+}
+
+/// Consecutive Destiny days (split at the daily reset) with at least one clear, as the
+/// current and longest runs. Today without a clear yet doesn't break the current streak.
+fn compute_streaks(
+    clear_times: impl Iterator<Item = DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> (u32, u32) {
+    let days: BTreeSet<NaiveDate> = clear_times
+        .map(|t| get_destiny_daily_reset_time(t).date_naive())
+        .collect();
+
+    let mut best = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for day in days.iter() {
+        run = match previous {
+            Some(p) if p.succ_opt() == Some(*day) => run + 1,
+            _ => 1,
+        };
+
+        best = best.max(run);
+        previous = Some(*day);
+    }
+
+    let today = get_destiny_daily_reset_time(now).date_naive();
+    let mut day = match days.contains(&today) {
+        true => today,
+        false => match today.pred_opt() {
+            Some(d) => d,
+            None => return (0, best),
+        },
+    };
+
+    let mut current = 0;
+
+    while days.contains(&day) {
+        current += 1;
+
+        day = match day.pred_opt() {
+            Some(d) => d,
+            None => break,
+        };
+    }
+
+    (current, best)
+}
+
+/// Adds this week's clears from the other tracked profiles onto the selected profile's counts.
+/// Their history isn't cached, since a cache holding only the current week would look complete.
+async fn add_tracked_clears(handle: &AppHandle, stats: &mut HistoryStats, now: DateTime<Utc>) {
+    let other_profiles = {
+        let container = handle.state::<ConfigContainer>();
+        let lock = container.0.lock().await;
+
+        lock.get_profiles().other_tracked_profiles()
+    };
+
+    let daily_reset = get_destiny_daily_reset_time(now);
+    let weekly_reset = get_destiny_weekly_reset_time(now);
+
+    for profile in other_profiles.iter() {
+        let profile_id = get_profile_id(profile);
+
+        // A failed fetch (e.g. throttled) keeps the profile's last count, so the totals don't dip
+        // until the next check. It never takes the selected profile's stats down with it.
+        let clear_times = match fetch_tracked_clear_times(handle, profile, weekly_reset).await {
+            Ok(times) => {
+                TRACKED_CLEAR_TIMES
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(profile_id, times.clone());
+
+                times
+            }
+            Err(e) => {
+                warn!(target: "history", "⚠️ History: Keeping last clears for tracked profile {}: {}", profile_id, e);
+
+                TRACKED_CLEAR_TIMES
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .get(&profile_id)
+                    .cloned()
+                    .unwrap_or_default()
+            }
+        };
+
+        // Counted against the current resets, so last good counts still roll over
+        stats.tracked_daily_clears += clear_times.iter().filter(|t| **t >= daily_reset).count();
+        stats.tracked_weekly_clears += clear_times.iter().filter(|t| **t >= weekly_reset).count();
+    }
+}
+
+/// When each of a profile's raid and dungeon clears since `weekly_reset` finished
+async fn fetch_tracked_clear_times(
+    handle: &AppHandle,
+    profile: &Profile,
+    weekly_reset: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>, ApiError> {
+    let api = handle.state::<Api>();
+    let profile_info = retry_transient(|| api.profile_info_source.get(profile)).await?;
+
+    let mut activities = Vec::new();
+
+    // Only raid and dungeon clears are counted, so PvP history isn't needed. Paging runs all the
+    // way back to the reset, however busy the week was.
+    for character_id in profile_info.character_ids.iter() {
+        for page in 0..ACTIVITY_FETCH_MAX_PAGES {
+            let history = retry_transient(|| {
+                Api::get_activity_history(profile, character_id, page, ACTIVITY_HISTORY_MODE)
+            })
+            .await?;
+
+            let page_activities = match history.into_completed_activities() {
+                Some(a) if !a.is_empty() => a,
+                _ => break,
+            };
+
+            let reached_reset = page_activities.iter().any(|a| a.period < weekly_reset);
+            activities.extend(page_activities.into_iter().filter(|a| a.period >= weekly_reset));
+
+            if reached_reset {
+                break;
+            }
+        }
+    }
+
+    // Only dedup within a profile, a run shared by two tracked accounts counts for both
+    dedup_activities(&mut activities);
+
+    let raid_dungeon_hashes = fetch_raid_dungeon_hashes(handle, &activities).await;
+
+    Ok(activities
+        .iter()
+        .filter(|a| a.completed && is_raid_or_dungeon(a, &raid_dungeon_hashes))
+        .map(|a| a.period)
+        .collect())
 }
 
 #[derive(Serialize, Clone)]
@@ -71,14 +451,83 @@ fn should_keep_activity(activity: &CompletedActivity, weekly_reset: DateTime<Utc
 pub struct PlayerData {
     current_activity: CurrentActivity,
     activity_history: Vec<CompletedActivity>,
+    #[serde(flatten)]
+    stats: HistoryStats,
     profile_info: ProfileInfo,
 }
 
+#[derive(Serialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct HistoryStats {
+    personal_bests: HashMap<usize, CompletedActivity>,
+    clear_stats: HashMap<usize, ClearStats>,
+    daily_clears: usize,
+    weekly_clears: usize,
+    /// Clears across every tracked profile, including the selected one
+    tracked_daily_clears: usize,
+    tracked_weekly_clears: usize,
+    /// Sent as timestamps so the overlay can count down without waiting for the next update
+    next_daily_reset: DateTime<Utc>,
+    next_weekly_reset: DateTime<Utc>,
+    current_streak: u32,
+    best_streak: u32,
+    season_clears: HashMap<u32, u32>,
+    /// Lets the UI apply the current season filter without its own copy of the season table
+    current_season_start: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ClearStats {
+    count: usize,
+    average_secs: usize,
+    median_secs: usize,
+    best_secs: usize,
+}
+
+impl ClearStats {
+    /// `None` without any durations. An even count takes the mean of the two middle durations.
+    fn from_durations(mut durations: Vec<usize>) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+
+        durations.sort_unstable();
+
+        let count = durations.len();
+        let mid = count / 2;
+
+        let median_secs = match count % 2 {
+            0 => (durations[mid - 1] + durations[mid]) / 2,
+            _ => durations[mid],
+        };
+
+        Some(Self {
+            count,
+            average_secs: durations.iter().sum::<usize>() / count,
+            median_secs,
+            best_secs: durations[0],
+        })
+    }
+}
+
 #[derive(Serialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PlayerDataStatus {
     last_update: Option<PlayerData>,
-    error: Option<String>,
+    error: Option<AppError>,
+    /// When the next poll goes out after Bungie throttled the last one, for a countdown
+    throttled_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RebuildProgress {
+    fetched: usize,
+    failed: usize,
+    total: usize,
+    /// Stopped early by `cancel_rebuild`
+    cancelled: bool,
 }
 
 #[derive(Serialize, Clone)]
@@ -87,20 +536,61 @@ struct CurrentActivity {
     start_date: DateTime<Utc>,
     activity_hash: usize,
     activity_info: Option<ActivityInfo>,
+    fireteam: Vec<FireteamMember>,
+    state: ActivityState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phase: Option<ActivityPhase>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum ActivityState {
+    Offline,
+    /// In game but not in an activity, e.g. orbit or the Tower
+    Orbit,
+    InActivity,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ActivityTick {
+    activity_hash: usize,
+    elapsed_millis: i64,
+}
+
+/// Locally computed values the overlay can refresh between Bungie polls
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct OverlayTick {
+    activity: Option<ActivityTick>,
+    millis_until_daily_reset: i64,
+    millis_until_weekly_reset: i64,
 }
 
 #[derive(Default)]
 pub struct PlayerDataPoller {
     task_handle: Option<JoinHandle<()>>,
+    tick_handle: Option<JoinHandle<()>>,
     current_playerdata: Arc<Mutex<PlayerDataStatus>>,
+    check_now: Arc<Notify>,
 }
 
 impl PlayerDataPoller {
-    pub async fn reset(&mut self, app_handle: AppHandle) {
+    /// Stops polling until the next `reset`, dropping a running fetch along with the cache lock
+    /// it may be holding
+    pub fn abort(&self) {
         if let Some(t) = self.task_handle.as_ref() {
             t.abort();
         }
 
+        if let Some(t) = self.tick_handle.as_ref() {
+            t.abort();
+        }
+    }
+
+    pub async fn reset(&mut self, app_handle: AppHandle) {
+        self.abort();
+
         {
             let mut lock = self.current_playerdata.lock().await;
             *lock = PlayerDataStatus::default();
@@ -109,6 +599,12 @@ impl PlayerDataPoller {
         }
 
         let playerdata_clone = self.current_playerdata.clone();
+        let check_now = self.check_now.clone();
+
+        self.tick_handle = Some(async_runtime::spawn(overlay_ticker(
+            app_handle.clone(),
+            self.current_playerdata.clone(),
+        )));
 
         self.task_handle = Some(async_runtime::spawn(async move {
             let profile = {
@@ -119,7 +615,7 @@ impl PlayerDataPoller {
                     Some(p) => p.clone(),
                     None => {
                         let mut lock = playerdata_clone.lock().await;
-                        lock.error = Some("No profile set".to_string());
+                        lock.error = Some(AppError::NotFound("No profile set".to_string()));
 
                         send_data_update(&app_handle, lock.clone());
                         return;
@@ -127,18 +623,28 @@ impl PlayerDataPoller {
                 }
             };
 
-            let profile_info = {
+            // During maintenance, keep waiting for Bungie to come back instead of giving up
+            let profile_info = loop {
                 let api = app_handle.state::<Api>();
-                let mut lock = api.profile_info_source.lock().await;
 
-                match lock.get(&profile).await {
-                    Ok(p) => p,
+                match api.profile_info_source.get(&profile).await {
+                    Ok(p) => break p,
                     Err(e) => {
-                        let mut lock = playerdata_clone.lock().await;
-                        lock.error = Some(format!("Failed to get profile info: {e}"));
+                        let e: AppError = e.into();
+                        let disabled = matches!(e, AppError::ServiceDisabled);
 
-                        send_data_update(&app_handle, lock.clone());
-                        return;
+                        {
+                            let mut lock = playerdata_clone.lock().await;
+                            lock.error = Some(e);
+
+                            send_data_update(&app_handle, lock.clone());
+                        }
+
+                        if !disabled {
+                            return;
+                        }
+
+                        tokio::time::sleep(SERVICE_DISABLED_POLL_INTERVAL).await;
                     }
                 }
             };
@@ -147,39 +653,128 @@ impl PlayerDataPoller {
                 start_date: DateTime::<Utc>::MIN_UTC,
                 activity_hash: 0,
                 activity_info: None,
+                fireteam: Vec::new(),
+                state: ActivityState::Offline,
+                phase: None,
             };
             let mut activity_history = Vec::new();
+            let mut stats = HistoryStats::default();
 
-            let res = match update_current(&app_handle, &mut current_activity, &profile).await {
-                Ok(_) => update_history(&app_handle, &mut activity_history, &profile).await,
-                Err(e) => Err(e),
-            };
-
-            {
-                let mut lock = playerdata_clone.lock().await;
-                match res {
+            loop {
+                let res = match update_current(&app_handle, &mut current_activity, &profile).await {
                     Ok(_) => {
-                        let playerdata = PlayerData {
-                            current_activity: current_activity,
-                            activity_history,
-                            profile_info,
+                        // Forward partial history to the UI while the initial fetch is still running
+                        let (history_tx, mut history_rx) = mpsc::channel(HISTORY_STREAM_CAPACITY);
+
+                        let forward = async {
+                            while let Some(history) = history_rx.recv().await {
+                                let mut lock = playerdata_clone.lock().await;
+
+                                match lock.last_update.as_mut() {
+                                    Some(data) => data.activity_history = history,
+                                    None => {
+                                        lock.last_update = Some(PlayerData {
+                                            current_activity: current_activity.clone(),
+                                            activity_history: history,
+                                            stats: HistoryStats::default(),
+                                            profile_info: profile_info.clone(),
+                                        })
+                                    }
+                                }
+
+                                send_data_update(&app_handle, lock.clone());
+                            }
                         };
 
-                        lock.last_update = Some(playerdata);
-                        send_data_update(&app_handle, lock.clone());
+                        let (res, _) = tokio::join!(
+                            update_history(
+                                &app_handle,
+                                &mut activity_history,
+                                &mut stats,
+                                &profile,
+                                Some(history_tx),
+                                false
+                            ),
+                            forward
+                        );
+
+                        res
                     }
-                    Err(e) => {
-                        lock.error = Some(e.to_string());
-                        send_data_update(&app_handle, lock.clone());
-                        return;
+                    Err(e) => Err(e),
+                };
+
+                {
+                    let mut lock = playerdata_clone.lock().await;
+                    match res {
+                        Ok(_) => {
+                            let playerdata = PlayerData {
+                                current_activity: current_activity,
+                                activity_history,
+                                stats,
+                                profile_info,
+                            };
+
+                            lock.error = None;
+                            lock.last_update = Some(playerdata);
+                            send_data_update(&app_handle, lock.clone());
+                            break;
+                        }
+                        Err(e) => {
+                            let e: AppError = e.into();
+                            let disabled = matches!(e, AppError::ServiceDisabled);
+
+                            lock.error = Some(e);
+                            send_data_update(&app_handle, lock.clone());
+
+                            if !disabled {
+                                return;
+                            }
+                        }
                     }
                 }
+
+                tokio::time::sleep(SERVICE_DISABLED_POLL_INTERVAL).await;
             }
 
             let mut count = 0;
+            let mut last_poll = Instant::now();
+            let mut service_disabled = false;
+            let mut consecutive_errors = 0;
+            let mut throttle_wait = Duration::ZERO;
 
             loop {
-                tokio::time::sleep(Duration::from_secs(POLLER_INTERVAL_SECS)).await;
+                let (poll_interval, history_check_interval, slow_when_closed) = {
+                    let container = app_handle.state::<ConfigContainer>();
+                    let lock = container.0.lock().await;
+                    let preferences = lock.get_preferences();
+
+                    (
+                        preferences.poll_interval(),
+                        preferences.history_check_interval(),
+                        preferences.slow_poll_when_game_closed,
+                    )
+                };
+
+                let interval = match service_disabled {
+                    true => SERVICE_DISABLED_POLL_INTERVAL,
+                    false => backoff_interval(poll_interval, consecutive_errors).max(throttle_wait),
+                };
+
+                let checking_now = tokio::select! {
+                    _ = tokio::time::sleep(interval) => false,
+                    _ = check_now.notified() => true,
+                };
+
+                // Keep checking for the game at the normal rate, but only hit Bungie occasionally
+                if !checking_now
+                    && slow_when_closed
+                    && last_poll.elapsed() < GAME_CLOSED_POLL_INTERVAL
+                    && !is_target_running()
+                {
+                    continue;
+                }
+
+                last_poll = Instant::now();
 
                 let mut last_update = match playerdata_clone.lock().await.last_update.clone() {
                     Some(data) => data,
@@ -188,28 +783,97 @@ impl PlayerDataPoller {
                     }
                 };
 
-                let res = if count < POLLER_HISTORY_CHECK_INTERVAL {
+                let res = if checking_now {
+                    count = 0;
+
+                    match update_current(&app_handle, &mut last_update.current_activity, &profile).await {
+                        Ok(current_changed) => update_history(
+                            &app_handle,
+                            &mut last_update.activity_history,
+                            &mut last_update.stats,
+                            &profile,
+                            None,
+                            true,
+                        )
+                        .await
+                        .map(|history_changed| current_changed || history_changed),
+                        Err(e) => Err(e),
+                    }
+                } else if count < history_check_interval {
                     update_current(&app_handle, &mut last_update.current_activity, &profile).await
                 } else {
                     count = 0;
-                    update_history(&app_handle, &mut last_update.activity_history, &profile).await
+                    update_history(
+                        &app_handle,
+                        &mut last_update.activity_history,
+                        &mut last_update.stats,
+                        &profile,
+                        None,
+                        false,
+                    )
+                    .await
                 };
 
+                let was_disabled = service_disabled;
+                let was_failing = consecutive_errors > 0;
+                let res = res.map_err(AppError::from);
+                service_disabled = matches!(res, Err(AppError::ServiceDisabled));
+
+                // Polls work on a copy, so the last good data (and with it the overlay timer) is only
+                // replaced once Bungie actually answers, never by a failed poll
                 match res {
-                    Ok(true) => {
+                    // Also clear the maintenance or outage error once Bungie responds again
+                    Ok(changed) if changed || was_disabled || was_failing => {
+                        consecutive_errors = 0;
+                        throttle_wait = Duration::ZERO;
+
                         let mut lock = playerdata_clone.lock().await;
                         lock.error = None;
+                        lock.throttled_until = None;
                         lock.last_update = Some(last_update);
 
                         send_data_update(&app_handle, lock.clone())
                     }
                     Err(e) => {
+                        consecutive_errors += 1;
+
+                        // Bungie says how long to back off, so the next poll and the UI's countdown
+                        // both wait at least that long
+                        throttle_wait = match &e {
+                            AppError::Throttled { seconds, .. } => Duration::from_secs((*seconds).max(0) as u64),
+                            _ => Duration::ZERO,
+                        };
+
+                        let wait = backoff_interval(poll_interval, consecutive_errors).max(throttle_wait);
+
+                        warn!(target: "poller", "⏳ Poller: {} consecutive errors, backing off to {:?}",
+                            consecutive_errors,
+                            wait
+                        );
+
                         let mut lock = playerdata_clone.lock().await;
-                        lock.error = Some(e.to_string());
 
-                        send_data_update(&app_handle, lock.clone())
+                        // The countdown stands in for the error, which would repeat on every retry
+                        if throttle_wait > Duration::ZERO {
+                            let wait = chrono::Duration::from_std(wait).unwrap_or_else(|_| chrono::Duration::zero());
+
+                            lock.error = None;
+                            lock.throttled_until = Some(Utc::now() + wait);
+                            send_data_update(&app_handle, lock.clone());
+                        } else {
+                            lock.throttled_until = None;
+
+                            // A blip shouldn't flash an error over an activity that's still going
+                            let brief = e.is_transient() && consecutive_errors < TRANSIENT_ERROR_GRACE_POLLS;
+
+                            // Repeating the same error on every retry only spams the UI
+                            if !brief && lock.error.as_ref().map(|l| l.to_string()) != Some(e.to_string()) {
+                                lock.error = Some(e);
+                                send_data_update(&app_handle, lock.clone())
+                            }
+                        }
                     }
-                    _ => (),
+                    _ => consecutive_errors = 0,
                 }
 
                 count += 1;
@@ -217,80 +881,359 @@ impl PlayerDataPoller {
         }));
     }
 
-    pub fn get_data(&mut self) -> Option<PlayerDataStatus> {
-        return match &self.current_playerdata.try_lock() {
-            Ok(p) => Some((*p).clone()),
-            Err(_) => None,
-        };
-    }
-}
-
-fn send_data_update(handle: &AppHandle, data: PlayerDataStatus) {
-    if let Some(o) = handle.get_window("overlay") {
-        let _ = o.emit("playerdata_update", data.clone());
+    /// Wakes the poller for an immediate current and history check, ignoring cache staleness
+    /// once. Requests made mid-poll are picked up as soon as it finishes.
+    pub fn check_now(&self) {
+        self.check_now.notify_one();
     }
 
-    if let Some(o) = handle.get_window("details") {
-        let _ = o.emit("playerdata_update", data);
+    /// The status the poller keeps updated, for readers outside the Tauri event system
+    pub fn shared_status(&self) -> Arc<Mutex<PlayerDataStatus>> {
+        self.current_playerdata.clone()
     }
-}
 
-async fn update_current(
-    handle: &AppHandle,
-    last_activity: &mut CurrentActivity,
-    profile: &Profile,
-) -> Result<bool> {
-    let current_activities = Api::get_profile_activities(profile).await?;
+    /// Shareable one-liner for the current activity, or the latest one if none is in progress,
+    /// e.g. "Last Wish — 18:42 (fresh) • 3 clears today"
+    pub async fn run_summary(&self, handle: &AppHandle, display_milliseconds: bool) -> Option<String> {
+        let (current, latest, daily_clears) = {
+            let lock = self.current_playerdata.lock().await;
+            let data = lock.last_update.as_ref()?;
+
+            let current = Some(&data.current_activity)
+                .filter(|a| a.activity_hash != 0)
+                .and_then(|a| a.activity_info.as_ref().map(|i| (i.name.clone(), a.start_date)));
+
+            (
+                current,
+                data.activity_history.iter().max().cloned(),
+                data.stats.daily_clears,
+            )
+        };
 
-    let activities = match current_activities.activities {
-        Some(a) => a,
-        None => bail!("Profile is private"),
-    };
+        let activity = match (current, latest) {
+            (Some((name, start_date)), _) => {
+                let elapsed = (Utc::now() - start_date).num_milliseconds().max(0);
 
-    let (characters, activities): (Vec<String>, Vec<LatestCharacterActivity>) =
-        activities.into_iter().unzip();
+                format!(
+                    "{name} — {} (in progress)",
+                    format_duration(elapsed, display_milliseconds)
+                )
+            }
+            (None, Some(latest)) => {
+                let api = handle.state::<Api>();
+                let name = api
+                    .activity_info_source
+                    .get(&latest.activity_hash)
+                    .await
+                    .map(|i| i.name)
+                    .ok()?;
+
+                let start = match latest.is_fresh_start() {
+                    Some(true) => " (fresh)",
+                    Some(false) => " (checkpoint)",
+                    None => "",
+                };
 
-    let latest_activity = activities
-        .into_iter()
-        .max()
-        .ok_or(anyhow!("No character data for profile"))?;
+                let outcome = if latest.completed { "" } else { " (incomplete)" };
 
-    match last_activity
-        .start_date
-        .cmp(&latest_activity.date_activity_started)
-    {
-        std::cmp::Ordering::Less => {
-            last_activity.start_date = latest_activity.date_activity_started
-        }
-        std::cmp::Ordering::Equal => {
-            if last_activity.activity_info.is_none() {
-                return Ok(false);
+                format!(
+                    "{name} — {}{start}{outcome}",
+                    format_duration(
+                        latest.activity_duration_seconds as i64 * 1000,
+                        display_milliseconds
+                    )
+                )
             }
+            (None, None) => return None,
+        };
 
-            if last_activity.activity_hash == latest_activity.current_activity_hash {
-                return Ok(false);
-            }
-        }
-        std::cmp::Ordering::Greater => return Ok(false),
+        let clears = match daily_clears {
+            1 => "1 clear today".to_string(),
+            n => format!("{n} clears today"),
+        };
+
+        Some(format!("{activity} • {clears}"))
     }
 
-    let api = handle.state::<Api>();
+    pub async fn get_data(&self) -> PlayerDataStatus {
+        self.current_playerdata.lock().await.clone()
+    }
 
-    api.profile_info_source
-        .lock()
-        .await
-        .set_characters(profile, characters);
+    /// Re-applies the history filters from cache after preferences change, without refetching
+    pub fn refresh_history(&self, handle: AppHandle) {
+        let playerdata = self.current_playerdata.clone();
 
-    if latest_activity.current_activity_hash == 0 {
-        last_activity.activity_info = None;
-        return Ok(true);
-    }
+        async_runtime::spawn(async move {
+            let profile = {
+                let container = handle.state::<ConfigContainer>();
+                let lock = container.0.lock().await;
 
-    let current_activity_info = {
-        let activity = api
-            .activity_info_source
+                match &lock.get_profiles().selected_profile {
+                    Some(p) => p.clone(),
+                    None => return,
+                }
+            };
+
+            let cached = {
+                let container = handle.state::<CacheContainer>();
+                let lock = container.0.lock().await;
+
+                lock.get_cached_activities(&get_profile_id(&profile))
+                    .map(|c| c.activities.clone())
+            };
+
+            let mut activities = match cached {
+                Some(a) => a,
+                None => return,
+            };
+
+            let weekly_reset = get_destiny_weekly_reset_time(Utc::now());
+            let raid_dungeon_hashes = fetch_raid_dungeon_hashes(&handle, &activities).await;
+
+            activities.retain(|activity| {
+                should_keep_activity(activity, weekly_reset, &raid_dungeon_hashes)
+            });
+
+            let history = present_history(&handle, &activities).await;
+
+            let mut lock = playerdata.lock().await;
+
+            if let Some(data) = lock.last_update.as_mut() {
+                data.activity_history = history;
+                send_data_update(&handle, lock.clone());
+            }
+        });
+    }
+
+    pub async fn get_personal_bests(&self) -> HashMap<usize, CompletedActivity> {
+        match self.current_playerdata.lock().await.last_update.as_ref() {
+            Some(data) => data.stats.personal_bests.clone(),
+            None => HashMap::new(),
+        }
+    }
+}
+
+/// Request concurrency for history pages and PGCRs, as configured in preferences
+async fn get_fetch_limits(handle: &AppHandle) -> (usize, usize) {
+    let container = handle.state::<ConfigContainer>();
+    let lock = container.0.lock().await;
+    let preferences = lock.get_preferences();
+
+    (preferences.fetch_concurrency, preferences.pgcr_concurrency)
+}
+
+async fn get_history_cutoff(handle: &AppHandle, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let container = handle.state::<ConfigContainer>();
+    let lock = container.0.lock().await;
+
+    lock.get_preferences().history_cutoff(now)
+}
+
+/// Only touches the tray when the count changes, since most updates don't change it
+fn update_tray_clears(handle: &AppHandle, clears: usize) {
+    if TRAY_DAILY_CLEARS.swap(clears, Ordering::Relaxed) == clears {
+        return;
+    }
+
+    let _ = handle
+        .tray_handle()
+        .get_item("daily_clears")
+        .set_title(format!("Clears today: {clears}"));
+}
+
+/// Doubles the poll interval for each consecutive failure, without going past the backoff cap
+/// unless the configured interval already does
+fn backoff_interval(poll_interval: Duration, consecutive_errors: u32) -> Duration {
+    poll_interval
+        .saturating_mul(2u32.saturating_pow(consecutive_errors))
+        .min(ERROR_BACKOFF_MAX_INTERVAL.max(poll_interval))
+}
+
+/// Emits the overlay's timer and reset countdowns every overlay poll, independent of the Bungie poll
+async fn overlay_ticker(handle: AppHandle, playerdata: Arc<Mutex<PlayerDataStatus>>) {
+    let mut interval = tokio::time::interval(OVERLAY_POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let o = match handle.get_window("overlay") {
+            Some(o) => o,
+            None => continue,
+        };
+
+        let now = Utc::now();
+
+        let activity = {
+            let lock = playerdata.lock().await;
+
+            lock.last_update
+                .as_ref()
+                .map(|d| &d.current_activity)
+                .filter(|a| a.activity_hash != 0 && a.activity_info.is_some())
+                .map(|a| ActivityTick {
+                    activity_hash: a.activity_hash,
+                    elapsed_millis: (now - a.start_date).num_milliseconds().max(0),
+                })
+        };
+
+        // Computed from now rather than the last update, so the countdown rolls over on reset
+        let tick = OverlayTick {
+            activity,
+            millis_until_daily_reset: (get_destiny_next_daily_reset_time(now) - now).num_milliseconds(),
+            millis_until_weekly_reset: (get_destiny_next_weekly_reset_time(now) - now).num_milliseconds(),
+        };
+
+        let _ = o.emit("overlay_tick", tick);
+    }
+}
+
+/// Sent only for clears that weren't in the cache before, unlike the periodic `playerdata_update`
+fn send_new_clears(handle: &AppHandle, clears: &[NewClear]) {
+    for label in ["overlay", "details"] {
+        if let Some(w) = handle.get_window(label) {
+            let _ = w.emit("new_clear", clears.to_vec());
+        }
+    }
+}
+
+fn send_rebuild_progress(handle: &AppHandle, progress: &RebuildProgress) {
+    if let Some(o) = handle.get_window("details") {
+        let _ = o.emit("rebuild_progress", progress.clone());
+    }
+}
+
+fn send_data_update(handle: &AppHandle, data: PlayerDataStatus) {
+    if let Some(d) = data.last_update.as_ref() {
+        update_tray_clears(handle, d.stats.tracked_daily_clears);
+    }
+
+    {
+        let current = data
+            .last_update
+            .as_ref()
+            .map(|d| &d.current_activity)
+            .and_then(|a| {
+                a.activity_info
+                    .as_ref()
+                    .map(|i| (a.activity_hash, i.name.as_str(), a.start_date))
+            });
+
+        // A panic elsewhere while holding the lock mustn't take the poller down with it
+        let presence = handle.state::<DiscordPresenceContainer>();
+        presence
+            .0
             .lock()
-            .await
+            .unwrap_or_else(|e| e.into_inner())
+            .update(current);
+    }
+
+    // Either window may be closing mid-emit, which is no reason to stop updating the other
+    for label in ["overlay", "details"] {
+        if let Some(w) = handle.get_window(label) {
+            if let Err(e) = w.emit("playerdata_update", data.clone()) {
+                warn!(target: "poller", "⚠️ Poller: Failed to send update to {}: {}", label, e);
+            }
+        }
+    }
+}
+
+async fn update_current(
+    handle: &AppHandle,
+    last_activity: &mut CurrentActivity,
+    profile: &Profile,
+) -> Result<bool> {
+    let current_activities = Api::get_profile_activities(profile).await?;
+
+    // Members can join or leave without the activity changing
+    let fireteam_changed = last_activity.fireteam != current_activities.fireteam;
+    last_activity.fireteam = current_activities.fireteam;
+
+    let activities = match current_activities.activities {
+        Some(a) => a,
+        None => return Err(AppError::ProfilePrivate.into()),
+    };
+
+    let (characters, activities): (Vec<String>, Vec<LatestCharacterActivity>) =
+        activities.into_iter().unzip();
+
+    // A new account, or one with every character deleted, just has nothing to show yet
+    let Some(latest_activity) = activities.into_iter().max() else {
+        handle
+            .state::<Api>()
+            .profile_info_source
+            .set_characters(profile, characters)
+            .await;
+
+        let state = match current_activities.online {
+            true => ActivityState::Orbit,
+            false => ActivityState::Offline,
+        };
+        let changed = fireteam_changed
+            || last_activity.state != state
+            || last_activity.activity_info.is_some()
+            || last_activity.phase.is_some();
+
+        last_activity.activity_info = None;
+        last_activity.phase = None;
+        last_activity.state = state;
+
+        return Ok(changed);
+    };
+
+    // Encounters advance without the activity changing, same as the fireteam
+    let phase_changed = last_activity.phase != latest_activity.phase;
+    last_activity.phase = latest_activity.phase.clone();
+    let details_changed = fireteam_changed || phase_changed;
+
+    // Transitory data can be hidden by privacy settings, so a mode type also counts as online
+    let idle_state = if current_activities.online
+        || latest_activity.current_activity_mode_type.map_or(false, |m| m != 0)
+    {
+        ActivityState::Orbit
+    } else {
+        ActivityState::Offline
+    };
+
+    match last_activity
+        .start_date
+        .cmp(&latest_activity.date_activity_started)
+    {
+        std::cmp::Ordering::Less => {
+            last_activity.start_date = latest_activity.date_activity_started
+        }
+        std::cmp::Ordering::Equal => {
+            // Logging off from orbit leaves the start date alone
+            if last_activity.activity_info.is_none() {
+                let state_changed = last_activity.state != idle_state;
+                last_activity.state = idle_state;
+
+                return Ok(details_changed || state_changed);
+            }
+
+            if last_activity.activity_hash == latest_activity.current_activity_hash {
+                return Ok(details_changed);
+            }
+        }
+        std::cmp::Ordering::Greater => return Ok(details_changed),
+    }
+
+    let api = handle.state::<Api>();
+
+    api.profile_info_source
+        .set_characters(profile, characters)
+        .await;
+
+    if latest_activity.current_activity_hash == 0
+        || latest_activity.current_activity_mode_type == Some(SOCIAL_ACTIVITY_MODE)
+    {
+        last_activity.activity_info = None;
+        last_activity.state = idle_state;
+        return Ok(true);
+    }
+
+    let current_activity_info = {
+        let activity = api
+            .activity_info_source
             .get(&latest_activity.current_activity_hash)
             .await;
 
@@ -298,19 +1241,23 @@ async fn update_current(
             Ok(a) => a,
             Err(ApiError::ResponseError(BungieResponseError::ResponseMissing)) => {
                 last_activity.activity_info = None;
+                last_activity.state = idle_state;
                 return Ok(true);
             }
             Err(e) => return Err(e.into()),
         }
     };
 
+    // Orbit has a definition, just without a name
     if current_activity_info.name.is_empty() {
         last_activity.activity_info = None;
+        last_activity.state = idle_state;
         return Ok(true);
     }
 
     last_activity.activity_hash = latest_activity.current_activity_hash;
     last_activity.activity_info = Some(current_activity_info);
+    last_activity.state = ActivityState::InActivity;
 
     Ok(true)
 }
@@ -318,116 +1265,258 @@ async fn update_current(
 async fn update_history(
     handle: &AppHandle,
     last_history: &mut Vec<CompletedActivity>,
+    stats: &mut HistoryStats,
     profile: &Profile,
+    history_tx: Option<mpsc::Sender<Vec<CompletedActivity>>>,
+    force_check: bool,
 ) -> Result<bool> {
     let api = handle.state::<Api>();
     let cache_container = handle.state::<CacheContainer>();
 
-    let profile_info = api.profile_info_source.lock().await.get(profile).await?;
-    let profile_id = format!("{}_{}", profile.account_platform, profile.account_id);
+    let profile_info = api.profile_info_source.get(profile).await?;
+    let profile_id = get_profile_id(profile);
 
     let now = chrono::Utc::now();
     let weekly_reset = get_destiny_weekly_reset_time(now);
+    let cutoff = get_history_cutoff(handle, now).await;
 
     let mut cache_manager = cache_container.0.lock().await;
     
     let cached_activities = cache_manager.get_cached_activities(&profile_id);
     
     if let Some(cache) = cached_activities {
-        #[cfg(debug_assertions)]
-        println!("📦 Cache: Found {} cached activities for profile {}", cache.activities.len(), profile_id);
+        debug!(target: "cache", "📦 Cache: Found {} cached activities for profile {}", cache.activities.len(), profile_id);
         
         let cache_age = now.signed_duration_since(cache.last_updated);
-        let should_check_updates = cache_age.num_minutes() >= CACHE_STALE_MINUTES;
+        let should_check_updates = force_check || cache_age.num_minutes() >= CACHE_STALE_MINUTES;
         
         if should_check_updates {
-            #[cfg(debug_assertions)]
-            println!("🔄 Cache: Checking for new activities (cache is {} minutes old)...", cache_age.num_minutes());
-            let mut recent_activities: Vec<CompletedActivity> = Vec::new();
+            debug!(target: "cache", "🔄 Cache: Checking for new activities (cache is {} minutes old)...", cache_age.num_minutes());
+
+            // Released while Bungie answers, so e.g. the cache stats in preferences aren't held up
+            drop(cache_manager);
+
+            let mut lookups = JoinSet::new();
 
             for character_id in profile_info.character_ids.iter() {
-                let history = Api::get_activity_history(profile, character_id, 0, ACTIVITY_HISTORY_PAGE_SIZE).await?;
-                if let Some(activities) = history.into_completed_activities() {
-                    recent_activities.extend(activities);
+                for mode in [ACTIVITY_HISTORY_MODE, PVP_HISTORY_MODE] {
+                    let (profile, character_id) = (profile.clone(), character_id.clone());
+
+                    lookups.spawn(async move {
+                        Api::get_activity_history(&profile, &character_id, 0, mode).await
+                    });
+                }
+            }
+
+            let mut recent_activities: Vec<CompletedActivity> = Vec::new();
+            let mut succeeded = 0;
+            let mut last_error = None;
+
+            while let Some(res) = lookups.join_next().await {
+                match res {
+                    Ok(Ok(history)) => {
+                        succeeded += 1;
+                        recent_activities.extend(history.into_completed_activities().unwrap_or_default());
+                    }
+                    Ok(Err(e)) => last_error = Some(e),
+                    Err(_) => (),
                 }
             }
+
+            // One character failing shouldn't hide another's new activities, but all of them failing is an outage
+            if let (0, Some(e)) = (succeeded, last_error) {
+                return Err(e.into());
+            }
+
+            cache_manager = cache_container.0.lock().await;
             
             if cache_manager.has_new_activities(&profile_id, &recent_activities) {
-                #[cfg(debug_assertions)]
-                println!("🔄 Cache: New activities detected, fetching updates...");
+                info!(target: "cache", "🔄 Cache: New activities detected, fetching updates...");
                 let mut new_activities: Vec<CompletedActivity> = Vec::new();
+                let known_ids: HashSet<String> = cache_manager
+                    .get_cached_activities(&profile_id)
+                    .map(|c| c.activities.iter().map(|a| a.instance_id.clone()).collect())
+                    .unwrap_or_default();
+
+                // Only the IDs are needed while walking and fetching PGCRs
+                drop(cache_manager);
                 
                 for character_id in profile_info.character_ids.iter() {
-                    for page in 0..5 {
-                        let history = Api::get_activity_history(profile, character_id, page, ACTIVITY_HISTORY_PAGE_SIZE).await?;
-                        if let Some(activities) = history.into_completed_activities() {
-                            if activities.is_empty() {
+                    for mode in [ACTIVITY_HISTORY_MODE, PVP_HISTORY_MODE] {
+                        for page in 0..ACTIVITY_FETCH_MAX_PAGES {
+                            let history = retry_transient(|| {
+                                Api::get_activity_history(profile, character_id, page, mode)
+                            })
+                            .await?;
+                            let activities = match history.into_completed_activities() {
+                                Some(a) if !a.is_empty() => a,
+                                _ => break,
+                            };
+
+                            // History is newest first, so everything past a cached activity is cached too.
+                            // PvP from before the reset is never cached, so that walk stops at the reset.
+                            let reached_cache = activities.iter().any(|a| known_ids.contains(&a.instance_id))
+                                || (mode == PVP_HISTORY_MODE && activities.iter().any(|a| a.period < weekly_reset));
+                            new_activities.extend(activities);
+
+                            if reached_cache {
                                 break;
                             }
-                            new_activities.extend(activities);
-                        } else {
-                            break;
                         }
                     }
                 }
                 
+                // Pages can shift while walking them, so overlap with the cache is still possible
+                dedup_activities(&mut new_activities);
+
+                if let Some(cutoff) = cutoff {
+                    new_activities.retain(|a| a.period >= cutoff);
+                }
+
                 // Fetch PGCR data for new activities
-                fetch_pgcrs_for_activities(&mut new_activities).await;
+                fetch_pgcrs_for_activities(handle, &mut new_activities, None, |_| ()).await;
+
+                let new_raid_dungeon_hashes = fetch_raid_dungeon_hashes(handle, &new_activities).await;
+                let families = api.activity_info_source.get_activity_families().await;
+
+                cache_manager = cache_container.0.lock().await;
+
+                let new_clears = find_new_clears(
+                    cache_manager.get_cached_activities(&profile_id),
+                    &new_activities,
+                    &new_raid_dungeon_hashes,
+                    &families,
+                );
                 
                 cache_manager.merge_activities(profile_id.clone(), new_activities);
+
+                if !new_clears.is_empty() {
+                    notify_new_clears(handle, new_clears).await;
+                }
             } else {
-                #[cfg(debug_assertions)]
-                println!("✅ Cache: No new activities found");
+                debug!(target: "cache", "✅ Cache: No new activities found");
             }
         } else {
-            #[cfg(debug_assertions)]
-            println!("✅ Cache: Using cached data (cache is {} minutes old, will check again in {} minutes)",
+            debug!(target: "cache", "✅ Cache: Using cached data (cache is {} minutes old, will check again in {} minutes)",
                 cache_age.num_minutes(), CACHE_STALE_MINUTES - cache_age.num_minutes());
         }
         
-        let final_cache = cache_manager.get_cached_activities(&profile_id)
-            .expect("cache entry exists after merge");
-        let mut all_activities = final_cache.activities.clone();
+        // A rebuild stopped while paging may be missing older history, so walk it again on launch.
+        // Activities already cached keep their PGCR data.
+        if history_tx.is_some() && cache_manager.is_partial(&profile_id) {
+            let _rebuild = RebuildGuard::start();
+
+            info!(target: "history", "🔁 History: Resuming a rebuild that was stopped while paging");
+
+            drop(cache_manager);
+            let walked = walk_all_history(handle, profile, &profile_info, weekly_reset, cutoff).await;
+            cache_manager = cache_container.0.lock().await;
+
+            match walked {
+                Ok(walked) => {
+                    cache_manager.merge_activities(profile_id.clone(), walked);
+                    cache_manager.set_partial(&profile_id, is_rebuild_cancelled());
+                    cache_manager.save_in_background();
+                }
+                // What's cached is still shown, and the marker stays for the next launch
+                Err(e) => warn!(target: "history", "⚠️ History: Failed to resume the stopped rebuild: {}", e),
+            }
+        }
+
+        // Pruned before resuming PGCR fetches, so none are fetched only to be thrown away
+        if let Some(cutoff) = cutoff {
+            cache_manager.prune_activities_before(&profile_id, cutoff);
+        }
+
+        // A rebuild killed part way leaves cached activities without PGCR data, so finish it on launch
+        if history_tx.is_some() {
+            let mut activities = cache_manager
+                .get_cached_activities(&profile_id)
+                .map(|c| c.activities.clone())
+                .unwrap_or_default();
+
+            if activities.iter().any(|a| !a.pgcr_fetched()) {
+                let _rebuild = RebuildGuard::start();
+
+                info!(target: "pgcr", "🔁 PGCR: Resuming fetch for cached activities without PGCR data");
+
+                stream_history(history_tx.as_ref(), &activities);
+
+                // Released while fetching, so exports, imports and clears aren't held up for the
+                // whole resume. Results are merged onto whatever is cached by then.
+                drop(cache_manager);
+
+                fetch_pgcrs_for_activities(handle, &mut activities, history_tx.as_ref(), |checkpoint| {
+                    // Best effort, a busy cache just waits for the next checkpoint
+                    if let Ok(mut cache_manager) = cache_container.0.try_lock() {
+                        cache_manager.merge_pgcr_data(&profile_id, checkpoint);
+                        cache_manager.save_in_background();
+                    }
+                })
+                .await;
+
+                cache_manager = cache_container.0.lock().await;
+                cache_manager.merge_pgcr_data(&profile_id, &activities);
+            }
+        }
+
+        // The lock is released while resuming PGCR fetches, so the cache may have been cleared since
+        let mut all_activities = match cache_manager.get_cached_activities(&profile_id) {
+            Some(final_cache) => final_cache.activities.clone(),
+            None => return Ok(false),
+        };
 
-        all_activities.retain(|activity| should_keep_activity(activity, weekly_reset));
-        
         cache_manager.save_in_background();
+
+        // Stats only need the copy, and their lookups can hit the network
+        drop(cache_manager);
+
+        let raid_dungeon_hashes = fetch_raid_dungeon_hashes(handle, &all_activities).await;
+        let families = api.activity_info_source.get_activity_families().await;
+        let mut new_stats =
+            compute_history_stats(&all_activities, now, &raid_dungeon_hashes, &families);
+        add_tracked_clears(handle, &mut new_stats, now).await;
+
+        all_activities.retain(|activity| {
+            should_keep_activity(activity, weekly_reset, &raid_dungeon_hashes)
+        });
+
+        // Counters still need to roll over at reset even when there's nothing new
+        let stats_changed = *stats != new_stats;
+        *stats = new_stats;
         
         if let Some(last) = last_history.iter().max() {
             if let Some(new) = all_activities.iter().max() {
                 if last >= new {
-                    return Ok(false);
+                    return Ok(stats_changed);
                 }
             }
         }
 
-        all_activities.sort_by(|a, b| b.period.cmp(&a.period));
-        *last_history = all_activities;
+        *last_history = present_history(handle, &all_activities).await;
         
         return Ok(true);
     }
     
-    #[cfg(debug_assertions)]
-    println!("🔍 Cache: No cache found, performing full activity fetch...");
-    #[cfg(debug_assertions)]
-    println!("📊 Fetching activities for {} characters with concurrent requests", profile_info.character_ids.len());
+    // The rebuild only takes the lock to save its progress, so exports, imports and clears
+    // aren't held up for the whole of it
+    drop(cache_manager);
+
+    info!(target: "cache", "🔍 Cache: No cache found, performing full activity fetch...");
+    info!(target: "history", "📊 Fetching activities for {} characters with concurrent requests", profile_info.character_ids.len());
     
-    let mut all_activities = fetch_all_activities_concurrent(profile, &profile_info, weekly_reset, &mut cache_manager, &profile_id).await?;
+    let _rebuild = RebuildGuard::start();
+    let all_activities = fetch_all_activities_concurrent(handle, profile, &profile_info, weekly_reset, cutoff, &profile_id, history_tx.as_ref()).await?;
     
-    #[cfg(debug_assertions)]
-    println!("🎉 Full fetch complete: {} total activities collected across all characters", all_activities.len());
+    info!(target: "history", "🎉 Full fetch complete: {} total activities collected across all characters", all_activities.len());
 
-    // Fetch PGCR data for all activities
-    #[cfg(debug_assertions)]
-    println!("💡 Note: You can use the app while PGCR data is being fetched in the background");
-    #[cfg(debug_assertions)]
-    println!("💡 Duration filters work immediately, checkpoint filters will work once PGCR fetch completes");
-    fetch_pgcrs_for_activities(&mut all_activities).await;
+    info!(target: "cache", "💾 Cache: Saving final cache with {} activities...", all_activities.len());
 
-    #[cfg(debug_assertions)]
-    println!("💾 Cache: Saving final cache with {} activities...", all_activities.len());
-    cache_manager.update_cache(profile_id.clone(), all_activities.clone());
-    cache_manager.save_in_background();
+    {
+        let mut cache_manager = cache_container.0.lock().await;
+        cache_manager.update_cache(profile_id.clone(), all_activities.clone());
+        cache_manager.save_in_background();
+    }
 
     if let Some(last) = last_history.iter().max() {
         if let Some(new) = all_activities.iter().max() {
@@ -437,285 +1526,1431 @@ async fn update_history(
         }
     }
 
-    all_activities.sort_by(|a, b| b.period.cmp(&a.period));
-
-    *last_history = all_activities;
+    let raid_dungeon_hashes = fetch_raid_dungeon_hashes(handle, &all_activities).await;
+    let families = api.activity_info_source.get_activity_families().await;
+    *stats = compute_history_stats(&all_activities, now, &raid_dungeon_hashes, &families);
+    add_tracked_clears(handle, stats, now).await;
+    *last_history = present_history(handle, &all_activities).await;
 
     Ok(true)
 }
 
-async fn fetch_pgcrs_for_activities(activities: &mut Vec<CompletedActivity>) {
-    use tokio::sync::Semaphore;
-    use std::sync::Arc;
-    use tokio::sync::Mutex as TokioMutex;
-    
-    let _total_activities = activities.len();
+/// Narrows the kept history down to what the user's filter preferences ask to see
+async fn present_history(
+    handle: &AppHandle,
+    activities: &[CompletedActivity],
+) -> Vec<CompletedActivity> {
+    let (filters, sorting) = {
+        let config = handle.state::<ConfigContainer>();
+        let lock = config.0.lock().await;
+        let preferences = lock.get_preferences();
+
+        (preferences.filters.clone(), preferences.sorting.clone())
+    };
 
-    // Count activities that need PGCR fetch (only those without PGCR data)
-    let needs_fetch = activities.iter()
-        .filter(|a| a.activity_was_started_from_beginning.is_none())
-        .count();
-    
-    if needs_fetch == 0 {
-        #[cfg(debug_assertions)]
-        println!("✅ PGCR: All {} activities already have PGCR data, skipping fetch", _total_activities);
-        return;
+    let filters = expand_specific_toggles(handle, filters, activities).await;
+
+    let mut history = apply_filters(activities, &filters);
+
+    let now = Utc::now();
+
+    if let Some(start) = time_range_start(sorting.time_range(), now) {
+        history.retain(|a| a.period >= start);
     }
-    
-    #[cfg(debug_assertions)]
-    println!("🎮 PGCR: Fetching PGCR data for {} activities (skipping {} already cached)...",
-        needs_fetch, _total_activities - needs_fetch);
-    #[cfg(debug_assertions)]
-    println!("⏱️  PGCR: Using {} concurrent requests for maximum throughput", PGCR_FETCH_CONCURRENCY);
-    #[cfg(debug_assertions)]
-    println!("📊 PGCR: Progress updates every {} activities...", crate::consts::PGCR_PROGRESS_INTERVAL);
 
-    let start_time = std::time::Instant::now();
-    let fetched = Arc::new(TokioMutex::new(0usize));
-    let failed = Arc::new(TokioMutex::new(0usize));
+    // Narrows the time range further rather than replacing it, e.g. "this week" still applies
+    if let Some(start) = current_season_start(now).filter(|_| filters.current_season_only) {
+        history.retain(|a| a.period >= start);
+    }
 
-    let semaphore = Arc::new(Semaphore::new(PGCR_FETCH_CONCURRENCY));
-    
-    // Collect ONLY activities that need PGCR fetch (missing activityWasStartedFromBeginning)
-    let fetch_list: Vec<(usize, String)> = activities.iter()
-        .enumerate()
-        .filter(|(_, a)| a.activity_was_started_from_beginning.is_none())
-        .map(|(i, a)| (i, a.instance_id.clone()))
-        .collect();
-    
-    let _total_to_fetch = fetch_list.len();
-    let mut handles = vec![];
+    sort_history(&mut history, &sorting);
 
-    for (_fetch_index, (activity_index, instance_id)) in fetch_list.into_iter().enumerate() {
-        let semaphore = semaphore.clone();
-        let fetched = fetched.clone();
-        let failed = failed.clone();
-        let _start_time_clone = start_time.clone();
-        
-        let handle = tokio::spawn(async move {
-            let _permit = semaphore.acquire().await.expect("semaphore not closed");
-
-            #[cfg(debug_assertions)]
-            if _fetch_index > 0 && _fetch_index % crate::consts::PGCR_PROGRESS_INTERVAL == 0 {
-                let elapsed = _start_time_clone.elapsed().as_secs();
-                let rate = if elapsed > 0 { _fetch_index as f64 / elapsed as f64 } else { 0.0 };
-                let remaining = _total_to_fetch - _fetch_index;
-                let eta = if rate > 0.0 { (remaining as f64 / rate) as u64 } else { 0 };
-                let f = *fetched.lock().await;
-                let fail = *failed.lock().await;
-                println!("   📊 Progress: {}/{} ({:.1}%) - Rate: {:.1}/s - ETA: {}s - Success: {}, Failed: {}",
-                    _fetch_index, _total_to_fetch, (_fetch_index as f64 / _total_to_fetch as f64) * 100.0,
-                    rate, eta, f, fail);
-            }
-            
-            match Api::get_pgcr(&instance_id).await {
-                Ok(pgcr) => {
-                    *fetched.lock().await += 1;
-                    Some((activity_index, pgcr))
-                }
-                Err(_e) => {
-                    let _fail_count = {
-                        let mut f = failed.lock().await;
-                        *f += 1;
-                        *f
-                    };
-                    #[cfg(debug_assertions)]
-                    if _fail_count <= crate::consts::PGCR_ERROR_LOG_LIMIT {
-                        eprintln!("   ⚠️ Failed to fetch PGCR for activity {}: {}", instance_id, _e);
-                    } else if _fail_count == crate::consts::PGCR_ERROR_LOG_LIMIT + 1 {
-                        eprintln!("   ⚠️ Suppressing further error messages...");
-                    }
-                    None
-                }
+    history
+}
+
+/// Ties on duration keep newest-first order (`sort_by` is stable). Activities without
+/// a recorded duration always sort last when ordering by duration.
+fn sort_history(history: &mut [CompletedActivity], sorting: &SortPreferences) {
+    let order = sorting.sort_order();
+
+    history.sort_by(|a, b| b.period.cmp(&a.period));
+
+    match sorting.sort_key() {
+        SortKey::Time if order == SortOrder::Ascending => history.reverse(),
+        SortKey::Time => (),
+        SortKey::Duration => history.sort_by(|a, b| {
+            let a = Some(a.activity_duration_seconds).filter(|d| *d > 0);
+            let b = Some(b.activity_duration_seconds).filter(|d| *d > 0);
+
+            match (a, b) {
+                (Some(a), Some(b)) => match order {
+                    SortOrder::Ascending => a.cmp(&b),
+                    SortOrder::Descending => b.cmp(&a),
+                },
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
             }
-        });
-        
-        handles.push(handle);
+        }),
     }
-    
-    // Wait for all requests to complete and update activities
-    #[cfg(debug_assertions)]
-    println!("⏳ PGCR: Waiting for {} concurrent requests to complete...", handles.len());
-    for handle in handles {
-        if let Ok(Some((activity_index, pgcr))) = handle.await {
-            if let Some(activity) = activities.get_mut(activity_index) {
-                activity.starting_phase_index = pgcr.starting_phase_index;
-                activity.activity_was_started_from_beginning = pgcr.activity_was_started_from_beginning;
+}
+
+/// Earliest period shown for the range, or `None` when everything is shown
+fn time_range_start(range: TimeRange, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match range {
+        TimeRange::Daily => Some(get_destiny_daily_reset_time(now)),
+        TimeRange::Weekly => Some(get_destiny_weekly_reset_time(now)),
+        TimeRange::Monthly => {
+            Some(get_destiny_weekly_reset_time(now) - chrono::Duration::weeks(MONTHLY_RANGE_WEEKS))
+        }
+        TimeRange::All => None,
+    }
+}
+
+/// Specific raid/dungeon toggles are keyed by a single hash per activity, but Bungie reissues
+/// activities under new hashes, so each toggle is extended to every hash sharing its name.
+async fn expand_specific_toggles(
+    handle: &AppHandle,
+    mut filters: FilterPreferences,
+    activities: &[CompletedActivity],
+) -> FilterPreferences {
+    if filters.specific_raids.is_empty() && filters.specific_dungeons.is_empty() {
+        return filters;
+    }
+
+    let api = handle.state::<Api>();
+
+    let hashes: HashSet<usize> = activities
+        .iter()
+        .map(|a| a.activity_hash)
+        .chain(filters.specific_raids.keys().map(|h| *h as usize))
+        .chain(filters.specific_dungeons.keys().map(|h| *h as usize))
+        .collect();
+
+    let mut names: HashMap<usize, String> = HashMap::new();
+
+    for hash in hashes {
+        if let Ok(info) = api.activity_info_source.get(&hash).await {
+            names.insert(hash, info.name);
+        }
+    }
+
+    for toggles in [&mut filters.specific_raids, &mut filters.specific_dungeons] {
+        let enabled: HashSet<&String> = toggles
+            .iter()
+            .filter(|(_, on)| **on)
+            .filter_map(|(hash, _)| names.get(&(*hash as usize)))
+            .collect();
+
+        for (hash, name) in names.iter() {
+            if enabled.contains(name) {
+                toggles.insert(*hash as u32, true);
             }
         }
     }
-    
-    #[cfg(debug_assertions)]
-    {
-        let elapsed = start_time.elapsed();
-        let f = *fetched.lock().await;
-        let fail = *failed.lock().await;
-        let rate = if elapsed.as_secs() > 0 { f as f64 / elapsed.as_secs_f64() } else { 0.0 };
-        println!("✅ PGCR: Completed in {:.1}s - Success: {}, Failed: {}, Rate: {:.1}/s",
-            elapsed.as_secs_f64(), f, fail, rate);
+
+    filters
+}
+
+#[derive(Clone, Copy)]
+enum ActivityType {
+    Raid,
+    Dungeon,
+    Strike,
+    LostSector,
+    Nightfall,
+    Crucible,
+    Trials,
+}
+
+impl ActivityType {
+    /// Same precedence as the UI: the first recognised mode wins, except that Trials games
+    /// also carry the Crucible mode and are always classified as Trials
+    fn from_modes(modes: &[usize]) -> Option<Self> {
+        if modes.contains(&TRIALS_ACTIVITY_MODE) {
+            return Some(ActivityType::Trials);
+        }
+
+        modes.iter().find_map(|m| match *m {
+            RAID_ACTIVITY_MODE => Some(ActivityType::Raid),
+            DUNGEON_ACTIVITY_MODE => Some(ActivityType::Dungeon),
+            STRIKE_ACTIVITY_MODE => Some(ActivityType::Strike),
+            LOSTSECTOR_ACTIVITY_MODE => Some(ActivityType::LostSector),
+            NIGHTFALL_ACTIVITY_MODE => Some(ActivityType::Nightfall),
+            CRUCIBLE_ACTIVITY_MODE => Some(ActivityType::Crucible),
+            _ => None,
+        })
     }
 }
 
-async fn fetch_all_activities_concurrent(
-    profile: &Profile,
-    profile_info: &ProfileInfo,
-    weekly_reset: DateTime<Utc>,
-    cache_manager: &mut tokio::sync::MutexGuard<'_, crate::cache::CacheManager>,
-    profile_id: &str,
-) -> Result<Vec<CompletedActivity>> {
-    use tokio::sync::Semaphore;
-    use std::sync::Arc;
-    use tokio::sync::Mutex as TokioMutex;
-    
-    let all_activities = Arc::new(TokioMutex::new(Vec::new()));
-    
-    let semaphore = Arc::new(Semaphore::new(ACTIVITY_FETCH_CONCURRENCY));
-    let mut handles = vec![];
+pub fn apply_filters(
+    activities: &[CompletedActivity],
+    prefs: &FilterPreferences,
+) -> Vec<CompletedActivity> {
+    activities
+        .iter()
+        .filter(|a| matches_filters(a, prefs))
+        .cloned()
+        .collect()
+}
 
-    #[cfg(debug_assertions)]
-    println!("📊 Starting concurrent fetch with {} parallel requests across {} characters", ACTIVITY_FETCH_CONCURRENCY, profile_info.character_ids.len());
+fn matches_filters(activity: &CompletedActivity, filters: &FilterPreferences) -> bool {
+    let type_match = match ActivityType::from_modes(&activity.modes) {
+        Some(ActivityType::Raid) => {
+            filters.show_raids && matches_specific(&filters.specific_raids, activity.activity_hash)
+        }
+        Some(ActivityType::Dungeon) => {
+            filters.show_dungeons
+                && matches_specific(&filters.specific_dungeons, activity.activity_hash)
+        }
+        Some(ActivityType::Strike) => filters.show_strikes,
+        Some(ActivityType::LostSector) => filters.show_lost_sectors,
+        Some(ActivityType::Nightfall) => filters.show_nightfalls,
+        Some(ActivityType::Crucible) => filters.show_crucible,
+        Some(ActivityType::Trials) => filters.show_trials,
+        None => false,
+    };
+
+    if !type_match {
+        return false;
+    }
+
+    // Runs whose PGCR hasn't been fetched yet pass both filters
+    if activity.is_fresh_start().is_some() {
+        let shown = match activity.is_fresh() {
+            true => filters.show_fresh_start,
+            false => filters.show_checkpoint,
+        };
+
+        if !shown {
+            return false;
+        }
+    }
+
+    if filters.show_flawless_only && !activity.flawless {
+        return false;
+    }
+
+    if activity.completed && !filters.show_completed {
+        return false;
+    }
+
+    if !activity.completed && !filters.show_incomplete {
+        return false;
+    }
+
+    // Activities whose PGCR hasn't been fetched yet pass, so a rebuild doesn't empty the list
+    if let Some(size) = activity.fireteam_size {
+        if filters.min_fireteam_size.map_or(false, |min| size < min)
+            || filters.max_fireteam_size.map_or(false, |max| size > max)
+        {
+            return false;
+        }
+    }
+
+    let duration = activity.activity_duration_seconds;
+
+    filters.min_duration_seconds.map_or(true, |min| duration >= min as usize)
+        && filters.max_duration_seconds.map_or(true, |max| duration <= max as usize)
+}
+
+/// No toggles means every activity of that type is shown
+fn matches_specific(toggles: &HashMap<u32, bool>, activity_hash: usize) -> bool {
+    toggles.is_empty() || toggles.get(&(activity_hash as u32)) == Some(&true)
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct NewClear {
+    #[serde(flatten)]
+    activity: CompletedActivity,
+    /// No earlier clear of this activity's family is cached, and the cache holds the whole history
+    first_clear: bool,
+}
+
+/// Completed raid and dungeon clears in `activities` that aren't already in `cache`. Reissues
+/// of an activity share its `families` entry, so clearing one isn't a first clear of the other.
+fn find_new_clears(
+    cache: Option<&ActivityCache>,
+    activities: &[CompletedActivity],
+    raid_dungeon_hashes: &HashSet<usize>,
+    families: &HashMap<usize, usize>,
+) -> Vec<NewClear> {
+    let cached = cache.map(|c| c.activities.as_slice()).unwrap_or_default();
+    let known: HashSet<&str> = cached.iter().map(|a| a.instance_id.as_str()).collect();
+    let mut cleared_families: HashSet<usize> = cached
+        .iter()
+        .filter(|a| a.completed)
+        .map(|a| family_hash(families, a.activity_hash))
+        .collect();
+
+    // An earlier clear could be in history the cache doesn't hold, or an empty cache has nothing
+    // to compare against, so nothing counts as a first clear then
+    let whole_history = cache.map_or(false, |c| !c.activities.is_empty() && !c.partial && !c.trimmed);
+
+    let mut seen = HashSet::new();
+
+    let mut clears: Vec<&CompletedActivity> = activities
+        .iter()
+        .filter(|a| a.completed && is_raid_or_dungeon(a, raid_dungeon_hashes))
+        .filter(|a| !known.contains(a.instance_id.as_str()))
+        .filter(|a| seen.insert(a.instance_id.as_str()))
+        .collect();
+
+    // Oldest first, so only the earliest of several new clears of one activity counts as the first
+    clears.sort_by_key(|a| a.period);
+
+    clears
+        .into_iter()
+        .map(|a| NewClear {
+            activity: a.clone(),
+            first_clear: whole_history
+                && cleared_families.insert(family_hash(families, a.activity_hash)),
+        })
+        .collect()
+}
+
+async fn notify_new_clears(handle: &AppHandle, clears: Vec<NewClear>) {
+    send_new_clears(handle, &clears);
+
+    let (webhook_url, show_toast, sound, display_milliseconds) = {
+        let config = handle.state::<ConfigContainer>();
+        let lock = config.0.lock().await;
+        let preferences = lock.get_preferences();
+
+        let url = preferences
+            .clear_webhook_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .map(str::to_string);
+
+        (
+            url,
+            preferences.display_clear_notifications,
+            preferences.notification_sound.clone(),
+            preferences.display_milliseconds,
+        )
+    };
+
+    if let Some(sound) = sound.filter(|_| show_toast) {
+        sound::play_notification_sound(&sound);
+    }
+
+    if webhook_url.is_none() && !show_toast {
+        return;
+    }
+
+    let api = handle.state::<Api>();
+    let mut named_clears = Vec::with_capacity(clears.len());
+
+    for clear in clears {
+        let name = api
+            .activity_info_source
+            .get(&clear.activity.activity_hash)
+            .await
+            .ok()
+            .map(|info| info.name);
+
+        named_clears.push((clear, name));
+    }
+
+    if show_toast {
+        show_clear_toast(handle, &named_clears, display_milliseconds);
+    }
+
+    if let Some(url) = webhook_url {
+        let payloads = named_clears
+            .into_iter()
+            .map(|(clear, name)| ClearPayload::new(&clear.activity, name, display_milliseconds))
+            .collect();
+
+        webhook::send_clears(url, payloads);
+    }
+}
+
+fn show_clear_toast(
+    handle: &AppHandle,
+    clears: &[(NewClear, Option<String>)],
+    display_milliseconds: bool,
+) {
+    let duration = |clear: &NewClear| {
+        format_duration(clear.activity.activity_duration_seconds as i64 * 1000, display_milliseconds)
+    };
+
+    // Collapse a large batch (e.g. after being offline for a while) into a single toast
+    let (title, body) = match clears {
+        [(clear, name)] if clear.first_clear => (
+            format!("First clear of {}!", name.as_deref().unwrap_or("this activity")),
+            format!("Clear time: {}", duration(clear)),
+        ),
+        [(clear, name)] => (
+            format!("{} completed!", name.as_deref().unwrap_or("Activity")),
+            format!("Clear time: {}", duration(clear)),
+        ),
+        _ if clears.len() <= CLEAR_TOAST_BATCH_LIMIT => (
+            format!("{} new clears", clears.len()),
+            clears
+                .iter()
+                .map(|(clear, name)| {
+                    format!("{} ({})", name.as_deref().unwrap_or("Activity"), duration(clear))
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => (
+            format!("{} new clears", clears.len()),
+            "Open threepole to see your history.".to_string(),
+        ),
+    };
+
+    let _ = Notification::new(&handle.config().tauri.bundle.identifier)
+        .title(title)
+        .body(body)
+        .show();
+}
+
+/// Sends a sorted snapshot of the history to the UI without ever waiting on a full channel.
+fn stream_history(
+    history_tx: Option<&mpsc::Sender<Vec<CompletedActivity>>>,
+    activities: &[CompletedActivity],
+) {
+    if let Some(tx) = history_tx {
+        let mut snapshot = activities.to_vec();
+        snapshot.sort_by(|a, b| b.period.cmp(&a.period));
+        let _ = tx.try_send(snapshot);
+    }
+}
+
+/// Caps in-flight requests with AIMD: the cap halves after `PGCR_THROTTLE_STREAK` throttled
+/// responses in a row and grows back by one after a full window (the current cap) of successes.
+/// It never drops below 1 or rises above `max`, the concurrency it starts at. Shrinking retires
+/// permits as they are released, so requests already in flight are never cut short.
+struct AdaptiveLimit {
+    semaphore: Semaphore,
+    max: usize,
+    limit: AtomicUsize,
+    // Permits to retire as they come back, rather than waiting to acquire them
+    pending_shrink: AtomicUsize,
+    throttle_streak: AtomicUsize,
+    success_streak: AtomicUsize,
+}
+
+impl AdaptiveLimit {
+    fn new(max: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(max),
+            max,
+            limit: AtomicUsize::new(max),
+            pending_shrink: AtomicUsize::new(0),
+            throttle_streak: AtomicUsize::new(0),
+            success_streak: AtomicUsize::new(0),
+        }
+    }
+
+    async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.semaphore.acquire().await.expect("semaphore not closed")
+    }
+
+    fn release(&self, permit: SemaphorePermit<'_>, throttled: bool) {
+        if throttled {
+            self.success_streak.store(0, Ordering::Relaxed);
+
+            if self.throttle_streak.fetch_add(1, Ordering::Relaxed) + 1 >= PGCR_THROTTLE_STREAK {
+                self.throttle_streak.store(0, Ordering::Relaxed);
+                self.shrink();
+            }
+        } else {
+            self.throttle_streak.store(0, Ordering::Relaxed);
+
+            let limit = self.limit.load(Ordering::Relaxed);
+
+            if self.success_streak.fetch_add(1, Ordering::Relaxed) + 1 >= limit {
+                self.success_streak.store(0, Ordering::Relaxed);
+                self.grow();
+            }
+        }
+
+        let retire = self
+            .pending_shrink
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+
+        if retire {
+            permit.forget();
+        }
+    }
+
+    fn shrink(&self) {
+        let limit = self.limit.load(Ordering::Relaxed);
+        let new_limit = (limit / 2).max(1);
+
+        if new_limit < limit
+            && self
+                .limit
+                .compare_exchange(limit, new_limit, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.pending_shrink.fetch_add(limit - new_limit, Ordering::Relaxed);
+
+            warn!(target: "pgcr", "🐢 PGCR: Throttled, lowering concurrency {} -> {}", limit, new_limit);
+        }
+    }
+
+    fn grow(&self) {
+        let limit = self.limit.load(Ordering::Relaxed);
+
+        if limit >= self.max
+            || self
+                .limit
+                .compare_exchange(limit, limit + 1, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+
+        // Cancel a shrink that hasn't been applied yet before handing out a new permit
+        let cancelled = self
+            .pending_shrink
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+
+        if !cancelled {
+            self.semaphore.add_permits(1);
+        }
+    }
+}
+
+/// Fills in PGCR fields, periodically handing partial progress to `on_checkpoint` so it can be persisted.
+async fn fetch_pgcrs_for_activities(
+    handle: &AppHandle,
+    activities: &mut Vec<CompletedActivity>,
+    history_tx: Option<&mpsc::Sender<Vec<CompletedActivity>>>,
+    mut on_checkpoint: impl FnMut(&[CompletedActivity]) + Send,
+) {
+    use tokio::sync::Mutex as TokioMutex;
+    
+    let total_activities = activities.len();
+
+    // Count activities that need PGCR fetch (only those without PGCR data)
+    let needs_fetch = activities.iter()
+        .filter(|a| !a.pgcr_fetched())
+        .count();
+    
+    if needs_fetch == 0 {
+        info!(target: "pgcr", "✅ PGCR: All {} activities already have PGCR data, skipping fetch", total_activities);
+        return;
+    }
+
+    let (_, pgcr_concurrency) = get_fetch_limits(handle).await;
+    
+    info!(target: "pgcr", "🎮 PGCR: Fetching PGCR data for {} activities (skipping {} already cached)...",
+        needs_fetch, total_activities - needs_fetch);
+    debug!(target: "pgcr", "⏱️  PGCR: Using {} concurrent requests for maximum throughput", pgcr_concurrency);
+    debug!(target: "pgcr", "📊 PGCR: Progress updates every {} activities...", PGCR_PROGRESS_INTERVAL);
+
+    let start_time = std::time::Instant::now();
+    let limit = Arc::new(AdaptiveLimit::new(pgcr_concurrency));
+    
+    // Collect ONLY activities that need PGCR fetch
+    let fetch_list: Vec<(usize, String)> = activities.iter()
+        .enumerate()
+        .filter(|(_, a)| !a.pgcr_fetched())
+        .map(|(i, a)| (i, a.instance_id.clone()))
+        .collect();
+    
+    let total_to_fetch = fetch_list.len();
+    let queue = Arc::new(TokioMutex::new(fetch_list.into_iter()));
+    let (result_tx, mut result_rx) = mpsc::channel(PGCR_RESULT_CHANNEL_CAPACITY);
+    // Dropping the set (e.g. when the poller task is aborted) aborts every worker
+    let mut workers = JoinSet::new();
+
+    for _ in 0..pgcr_concurrency.min(total_to_fetch) {
+        let limit = limit.clone();
+        let queue = queue.clone();
+        let result_tx = result_tx.clone();
+
+        workers.spawn(async move {
+            loop {
+                // Requests already in flight still finish, so their reports are kept
+                if is_rebuild_cancelled() {
+                    break;
+                }
+
+                let (activity_index, instance_id) = match queue.lock().await.next() {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                let permit = limit.acquire().await;
+                let res = Api::get_pgcr(&instance_id).await;
+                limit.release(permit, matches!(&res, Err(e) if e.is_throttling()));
+
+                // The consumer only goes away when the whole fetch is being dropped
+                if result_tx.send((activity_index, instance_id, res)).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    // Workers hold the remaining senders, so the channel closes once they've all finished
+    drop(result_tx);
+
+    debug!(target: "pgcr", "⏳ PGCR: Waiting for {} concurrent requests to complete...", total_to_fetch);
+    let mut last_checkpoint = std::time::Instant::now();
+    let mut since_checkpoint = 0;
+    let mut since_stream = 0;
+    let mut progress = RebuildProgress {
+        fetched: 0,
+        failed: 0,
+        total: total_to_fetch,
+        cancelled: false,
+    };
+
+    send_rebuild_progress(handle, &progress);
+
+    while let Some((activity_index, instance_id, res)) = result_rx.recv().await {
+        match res {
+            Ok(pgcr) => {
+                progress.fetched += 1;
+
+                if let Some(activity) = activities.get_mut(activity_index) {
+                    activity.apply_pgcr(&pgcr);
+                    since_checkpoint += 1;
+                    since_stream += 1;
+                }
+            }
+            Err(e) => {
+                progress.failed += 1;
+
+                if progress.failed <= crate::consts::PGCR_ERROR_LOG_LIMIT {
+                    warn!(target: "pgcr", "   ⚠️ Failed to fetch PGCR for activity {}: {}", instance_id, e);
+                } else if progress.failed == crate::consts::PGCR_ERROR_LOG_LIMIT + 1 {
+                    warn!(target: "pgcr", "   ⚠️ Suppressing further error messages...");
+                }
+            }
+        }
+
+        let done = progress.fetched + progress.failed;
+
+        if done % PGCR_PROGRESS_INTERVAL == 0 {
+            send_rebuild_progress(handle, &progress);
+
+            {
+                let elapsed = start_time.elapsed().as_secs();
+                let rate = if elapsed > 0 { done as f64 / elapsed as f64 } else { 0.0 };
+                let remaining = total_to_fetch - done;
+                let eta = if rate > 0.0 { (remaining as f64 / rate) as u64 } else { 0 };
+                debug!(target: "pgcr", "   📊 Progress: {}/{} ({:.1}%) - Rate: {:.1}/s - ETA: {}s - Success: {}, Failed: {}",
+                    done, total_to_fetch, (done as f64 / total_to_fetch as f64) * 100.0,
+                    rate, eta, progress.fetched, progress.failed);
+            }
+        }
+
+        if since_stream >= PGCR_STREAM_INTERVAL {
+            stream_history(history_tx, activities);
+            since_stream = 0;
+        }
+
+        if since_checkpoint >= PGCR_CHECKPOINT_INTERVAL
+            || (since_checkpoint > 0 && last_checkpoint.elapsed() >= PGCR_CHECKPOINT_MAX_AGE)
+        {
+            debug!(target: "pgcr", "💾 PGCR: Checkpointing progress after {} new reports", since_checkpoint);
+            on_checkpoint(activities);
+            last_checkpoint = std::time::Instant::now();
+            since_checkpoint = 0;
+        }
+    }
+
+    while workers.join_next().await.is_some() {}
     
-    for (_char_index, character_id) in profile_info.character_ids.iter().enumerate() {
+    if since_stream > 0 {
+        stream_history(history_tx, activities);
+    }
+
+    progress.cancelled = is_rebuild_cancelled();
+    send_rebuild_progress(handle, &progress);
+
+    {
+        let elapsed = start_time.elapsed();
+        let rate = if elapsed.as_secs() > 0 { progress.fetched as f64 / elapsed.as_secs_f64() } else { 0.0 };
+        info!(target: "pgcr", "✅ PGCR: Completed in {:.1}s - Success: {}, Failed: {}, Rate: {:.1}/s",
+            elapsed.as_secs_f64(), progress.fetched, progress.failed, rate);
+    }
+}
+
+/// Walks a profile's whole history and fetches its PGCRs, taking the cache lock only to save
+/// progress along the way. Saving the final result is left to the caller.
+async fn fetch_all_activities_concurrent(
+    handle: &AppHandle,
+    profile: &Profile,
+    profile_info: &ProfileInfo,
+    weekly_reset: DateTime<Utc>,
+    cutoff: Option<DateTime<Utc>>,
+    profile_id: &str,
+    history_tx: Option<&mpsc::Sender<Vec<CompletedActivity>>>,
+) -> Result<Vec<CompletedActivity>> {
+    let cache_container = handle.state::<CacheContainer>();
+    let mut all_activities = walk_all_history(handle, profile, profile_info, weekly_reset, cutoff).await?;
+
+    // Saved straight away, so a rebuild stopped while paging is picked up again on the next launch
+    {
+        let mut cache_manager = cache_container.0.lock().await;
+        cache_manager.update_cache(profile_id.to_string(), all_activities.clone());
+        cache_manager.set_partial(profile_id, is_rebuild_cancelled());
+
+        // The walk stopped at the cutoff, so anything older was never fetched
+        if cutoff.is_some() {
+            cache_manager.set_trimmed(profile_id);
+        }
+
+        cache_manager.save_in_background();
+    }
+
+    stream_history(history_tx, &all_activities);
+    
+    // Fetch PGCR data for all activities
+    debug!(target: "pgcr", "💡 Note: You can use the app while PGCR data is being fetched in the background");
+    debug!(target: "pgcr", "💡 Duration filters work immediately, checkpoint filters will work once PGCR fetch completes");
+    fetch_pgcrs_for_activities(handle, &mut all_activities, history_tx, |checkpoint| {
+        // Best effort, a busy cache just waits for the next checkpoint
+        if let Ok(mut cache_manager) = cache_container.0.try_lock() {
+            cache_manager.update_cache(profile_id.to_string(), checkpoint.to_vec());
+            cache_manager.save_in_background();
+        }
+    })
+    .await;
+
+    Ok(all_activities)
+}
+
+/// Every kept activity in a profile's history since `cutoff`, walking each character's PvE and
+/// PvP history concurrently. Stops short, without an error, if the rebuild is cancelled.
+async fn walk_all_history(
+    handle: &AppHandle,
+    profile: &Profile,
+    profile_info: &ProfileInfo,
+    weekly_reset: DateTime<Utc>,
+    cutoff: Option<DateTime<Utc>>,
+) -> Result<Vec<CompletedActivity>> {
+    let (fetch_concurrency, _) = get_fetch_limits(handle).await;
+    
+    let semaphore = Arc::new(Semaphore::new(fetch_concurrency));
+    // Dropping the set (e.g. when the poller task is aborted) aborts every walk and its workers
+    let mut handles = JoinSet::new();
+
+    info!(target: "history", "📊 Starting concurrent fetch with {} parallel requests across {} characters", fetch_concurrency, profile_info.character_ids.len());
+    
+    // The PvE walk runs back to the cutoff or the start of the history, the PvP one only needs
+    // this week's
+    let walks = profile_info.character_ids.iter().enumerate().flat_map(|(char_index, character_id)| {
+        [ACTIVITY_HISTORY_MODE, PVP_HISTORY_MODE].map(|mode| (char_index, character_id, mode))
+    });
+
+    for (char_index, character_id, mode) in walks {
         let character_id = character_id.clone();
         let profile = profile.clone();
-        let all_activities = all_activities.clone();
         let semaphore = semaphore.clone();
-        let _char_count = profile_info.character_ids.len();
-        let weekly_reset = weekly_reset.clone();
+        let char_count = profile_info.character_ids.len();
         
-        let handle = tokio::spawn(async move {
-            #[cfg(debug_assertions)]
-            println!("👤 Character {}/{}: Starting fetch for character ID {}", _char_index + 1, _char_count, character_id);
-            
-            let mut worker_handles = vec![];
-            let next_page = Arc::new(TokioMutex::new(0usize));
-            let should_stop = Arc::new(TokioMutex::new(false));
-            let total_collected = Arc::new(TokioMutex::new(0usize));
-
-            for _worker_id in 0..ACTIVITY_FETCH_WORKERS {
-                let semaphore = semaphore.clone();
-                let profile = profile.clone();
+        handles.spawn(async move {
+            debug!(target: "history", "👤 Character {}/{}: Starting mode {} fetch for character ID {}", char_index + 1, char_count, mode, character_id);
+
+            let fetch_page = {
                 let character_id = character_id.clone();
-                let all_activities = all_activities.clone();
-                let weekly_reset = weekly_reset.clone();
-                let next_page = next_page.clone();
-                let should_stop = should_stop.clone();
-                let total_collected = total_collected.clone();
-                
-                let worker_handle = tokio::spawn(async move {
-                    loop {
-                        // Check if we should stop
-                        if *should_stop.lock().await {
-                            break;
-                        }
-                        
-                        // Get next page to fetch
-                        let page = {
-                            let mut np = next_page.lock().await;
-                            if *np >= ACTIVITY_FETCH_MAX_PAGES {
-                                break;
-                            }
-                            let p = *np;
-                            *np += 1;
-                            p
-                        };
-                        
-                        let _permit = semaphore.acquire().await.expect("semaphore not closed");
 
-                        let history = match Api::get_activity_history(&profile, &character_id, page, ACTIVITY_HISTORY_PAGE_SIZE).await {
-                            Ok(h) => h,
-                            Err(_) => {
-                                *should_stop.lock().await = true;
-                                break;
-                            }
-                        };
-                        
-                        drop(_permit); // Release permit immediately after API call
-                        
-                        let activities = match history.into_completed_activities() {
-                            Some(a) => a,
-                            None => {
-                                *should_stop.lock().await = true;
-                                break;
-                            }
-                        };
-                        
-                        if activities.is_empty() {
-                            *should_stop.lock().await = true;
-                            break;
-                        }
-                        
-                        let mut collected = 0;
+                move |page| {
+                    let (profile, character_id, semaphore) = (profile.clone(), character_id.clone(), semaphore.clone());
 
-                        for activity in activities.into_iter() {
-                            if should_keep_activity(&activity, weekly_reset) {
-                                all_activities.lock().await.push(activity);
-                                collected += 1;
-                            }
-                        }
-                        
-                        *total_collected.lock().await += collected;
+                    // The permit is only held per attempt, not across a retry's backoff
+                    async move {
+                        let _permit = semaphore.acquire().await.expect("semaphore not closed");
+
+                        Api::get_activity_history(&profile, &character_id, page, mode)
+                            .await
+                            .map(|h| h.into_completed_activities())
                     }
-                });
-                
-                worker_handles.push(worker_handle);
-            }
-            
-            // Wait for all workers to complete
-            for handle in worker_handles {
-                let _ = handle.await;
+                }
+            };
+
+            let past_retention = move |page: &[CompletedActivity]| {
+                cutoff.map_or(false, |cutoff| is_past_cutoff(page, cutoff))
+                    || (mode == PVP_HISTORY_MODE && is_past_retention(page, weekly_reset))
+            };
+            let walked = walk_history_pages(fetch_page, past_retention).await;
+
+            match &walked {
+                Ok(activities) => debug!(target: "history", "   ✅ Character {}/{}: Completed mode {} fetch - {} activities collected",
+                    char_index + 1, char_count, mode, activities.len()),
+                Err(e) => warn!(target: "history", "⚠️ History: Mode {} fetch failed for character {}: {}", mode, character_id, e),
             }
-            
-            let _final_page = *next_page.lock().await;
-            let _final_collected = *total_collected.lock().await;
 
-            #[cfg(debug_assertions)]
-            println!("   ✅ Character {}/{}: Completed {} pages - {} activities collected",
-                _char_index + 1, _char_count, _final_page, _final_collected);
+            walked
         });
-        
-        handles.push(handle);
     }
     
-    // Wait for all character fetches to complete
-    for handle in handles {
-        let _ = handle.await;
+    let mut all_activities = Vec::new();
+
+    // A walk that failed would leave a gap in the history, so the whole fetch fails instead of
+    // saving it as complete. The remaining walks are aborted as the set is dropped.
+    while let Some(res) = handles.join_next().await {
+        if let Ok(walked) = res {
+            all_activities.extend(walked?);
+        }
     }
+
+    dedup_activities(&mut all_activities);
+
+    // The last page walked can reach past the cutoff
+    if let Some(cutoff) = cutoff {
+        all_activities.retain(|a| a.period >= cutoff);
+    }
+
+    let raid_dungeon_hashes = fetch_raid_dungeon_hashes(handle, &all_activities).await;
+    all_activities.retain(|activity| {
+        should_keep_activity(activity, weekly_reset, &raid_dungeon_hashes)
+    });
     
-    let mut all_activities = match Arc::try_unwrap(all_activities) {
-        Ok(mutex) => mutex.into_inner(),
-        Err(arc) => arc.lock().await.clone(),
-    };
-    
-    #[cfg(debug_assertions)]
-    println!("🎉 Concurrent fetch complete: {} total activities collected", all_activities.len());
-    
-    // Fetch PGCR data for all activities
-    #[cfg(debug_assertions)]
-    println!("💡 Note: You can use the app while PGCR data is being fetched in the background");
-    #[cfg(debug_assertions)]
-    println!("💡 Duration filters work immediately, checkpoint filters will work once PGCR fetch completes");
-    fetch_pgcrs_for_activities(&mut all_activities).await;
-    
-    #[cfg(debug_assertions)]
-    println!("💾 Cache: Saving final cache with {} activities...", all_activities.len());
-    cache_manager.update_cache(profile_id.to_string(), all_activities.clone());
-    cache_manager.save_in_background();
+    info!(target: "history", "🎉 Concurrent fetch complete: {} total activities collected", all_activities.len());
 
     Ok(all_activities)
 }
 
-fn get_destiny_weekly_reset_time(date: DateTime<Utc>) -> DateTime<Utc> {
-    let mut reset_time = DateTime::<Utc>::from_utc(
-        date.date_naive().and_hms_opt(DESTINY_DAILY_RESET_HOUR, 0, 0)
+/// Pages through one history query with `ACTIVITY_FETCH_WORKERS` workers sharing the page counter,
+/// until a page comes back empty or `past_retention` says everything after it gets pruned.
+/// A page that still fails after its transient retries fails the walk rather than leaving a gap.
+async fn walk_history_pages<F, Fut, P>(
+    fetch_page: F,
+    past_retention: P,
+) -> Result<Vec<CompletedActivity>, ApiError>
+where
+    F: Fn(usize) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<Vec<CompletedActivity>>, ApiError>> + Send + 'static,
+    P: Fn(&[CompletedActivity]) -> bool + Clone + Send + 'static,
+{
+    let activities = Arc::new(Mutex::new(Vec::new()));
+    let next_page = Arc::new(Mutex::new(0usize));
+    let should_stop = Arc::new(Mutex::new(false));
+    let failure = Arc::new(Mutex::new(None));
+    let mut workers = JoinSet::new();
+
+    for _ in 0..ACTIVITY_FETCH_WORKERS {
+        let fetch_page = fetch_page.clone();
+        let past_retention = past_retention.clone();
+        let activities = activities.clone();
+        let next_page = next_page.clone();
+        let should_stop = should_stop.clone();
+        let failure = failure.clone();
+
+        workers.spawn(async move {
+            loop {
+                if *should_stop.lock().await || is_rebuild_cancelled() {
+                    break;
+                }
+
+                let page = {
+                    let mut np = next_page.lock().await;
+                    if *np >= ACTIVITY_FETCH_MAX_PAGES {
+                        break;
+                    }
+                    let p = *np;
+                    *np += 1;
+                    p
+                };
+
+                let page_activities = match retry_transient(|| fetch_page(page)).await {
+                    Ok(Some(a)) if !a.is_empty() => a,
+                    Ok(_) => {
+                        *should_stop.lock().await = true;
+                        break;
+                    }
+                    Err(e) => {
+                        warn!(target: "history", "⚠️ History: Page {} failed after retrying: {}", page, e);
+                        failure.lock().await.get_or_insert(e);
+                        *should_stop.lock().await = true;
+                        break;
+                    }
+                };
+
+                let done = past_retention(&page_activities);
+                activities.lock().await.extend(page_activities);
+
+                if done {
+                    *should_stop.lock().await = true;
+                    break;
+                }
+            }
+        });
+    }
+
+    while workers.join_next().await.is_some() {}
+
+    if let Some(e) = failure.lock().await.take() {
+        return Err(e);
+    }
+
+    let activities = std::mem::take(&mut *activities.lock().await);
+
+    Ok(activities)
+}
+
+fn get_destiny_daily_reset_time(date: DateTime<Utc>) -> DateTime<Utc> {
+    let reset_time = Utc.from_utc_datetime(
+        &date
+            .date_naive()
+            .and_hms_opt(DESTINY_DAILY_RESET_HOUR, 0, 0)
             .expect("valid constant time"),
-        Utc
     );
-    
+
+    // Before today's reset, the current Destiny day started at yesterday's
     if date < reset_time {
-        reset_time = reset_time - chrono::Duration::days(1);
+        reset_time - chrono::Duration::days(1)
+    } else {
+        reset_time
+    }
+}
+
+fn get_destiny_weekly_reset_time(date: DateTime<Utc>) -> DateTime<Utc> {
+    let daily_reset = get_destiny_daily_reset_time(date);
+
+    // The weekly reset is the most recent daily reset that fell on a Tuesday
+    let days_since_tuesday = (daily_reset.weekday().num_days_from_monday() + 7
+        - Weekday::Tue.num_days_from_monday())
+        % 7;
+
+    daily_reset - chrono::Duration::days(days_since_tuesday as i64)
+}
+
+fn get_destiny_next_daily_reset_time(date: DateTime<Utc>) -> DateTime<Utc> {
+    get_destiny_daily_reset_time(date) + chrono::Duration::days(1)
+}
+
+fn get_destiny_next_weekly_reset_time(date: DateTime<Utc>) -> DateTime<Utc> {
+    get_destiny_weekly_reset_time(date) + chrono::Duration::weeks(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap()
+    }
+
+    fn activity(instance_id: &str, period: DateTime<Utc>, modes: &[usize]) -> CompletedActivity {
+        CompletedActivity {
+            period,
+            instance_id: instance_id.to_string(),
+            activity_hash: 1,
+            modes: modes.to_vec(),
+            completed: true,
+            activity_duration: String::new(),
+            activity_duration_seconds: 0,
+            completion_reason: 0,
+            starting_phase_index: None,
+            activity_was_started_from_beginning: None,
+            fireteam_size: None,
+            kills: None,
+            deaths: None,
+            assists: None,
+            flawless: false,
+            pgcr_version: 0,
+        }
+    }
+
+    fn full_page(period: DateTime<Utc>, modes: &[usize]) -> Vec<CompletedActivity> {
+        (0..ACTIVITY_HISTORY_PAGE_SIZE)
+            .map(|i| activity(&i.to_string(), period - chrono::Duration::minutes(i as i64), modes))
+            .collect()
+    }
+
+    #[test]
+    fn weekly_reset_just_before_tuesday_reset() {
+        // Tuesday 16:59:59 is still the previous week
+        assert_eq!(
+            get_destiny_weekly_reset_time(utc(2024, 1, 9, 16, 59, 59)),
+            utc(2024, 1, 2, 17, 0, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_reset_at_tuesday_reset() {
+        assert_eq!(
+            get_destiny_weekly_reset_time(utc(2024, 1, 9, 17, 0, 0)),
+            utc(2024, 1, 9, 17, 0, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_reset_just_after_tuesday_reset() {
+        assert_eq!(
+            get_destiny_weekly_reset_time(utc(2024, 1, 9, 17, 0, 1)),
+            utc(2024, 1, 9, 17, 0, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_reset_on_monday() {
+        // The last day of a week that started the Tuesday before
+        assert_eq!(
+            get_destiny_weekly_reset_time(utc(2024, 1, 8, 20, 0, 0)),
+            utc(2024, 1, 2, 17, 0, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_reset_on_wednesday() {
+        // Before Wednesday's daily reset is still Tuesday in Destiny terms
+        assert_eq!(
+            get_destiny_weekly_reset_time(utc(2024, 1, 10, 3, 0, 0)),
+            utc(2024, 1, 9, 17, 0, 0)
+        );
+        assert_eq!(
+            get_destiny_weekly_reset_time(utc(2024, 1, 10, 20, 0, 0)),
+            utc(2024, 1, 9, 17, 0, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_reset_across_year_boundary() {
+        // Monday 1 January 2024 belongs to the week that started Tuesday 26 December 2023
+        assert_eq!(
+            get_destiny_weekly_reset_time(utc(2024, 1, 1, 12, 0, 0)),
+            utc(2023, 12, 26, 17, 0, 0)
+        );
+    }
+
+    #[test]
+    fn past_retention_when_full_page_predates_reset() {
+        let reset = utc(2024, 1, 9, 17, 0, 0);
+        let page = full_page(reset - chrono::Duration::seconds(1), &[CRUCIBLE_ACTIVITY_MODE]);
+
+        assert!(is_past_retention(&page, reset));
+    }
+
+    #[test]
+    fn not_past_retention_with_activity_at_reset() {
+        let reset = utc(2024, 1, 9, 17, 0, 0);
+        let page = full_page(reset, &[CRUCIBLE_ACTIVITY_MODE]);
+
+        // Its newest activity started exactly at the reset, so it's still this week's
+        assert!(!is_past_retention(&page, reset));
+    }
+
+    #[test]
+    fn not_past_retention_with_raid_on_page() {
+        let reset = utc(2024, 1, 9, 17, 0, 0);
+        let mut page = full_page(reset - chrono::Duration::days(1), &[CRUCIBLE_ACTIVITY_MODE]);
+        page[10].modes = vec![RAID_ACTIVITY_MODE];
+
+        assert!(!is_past_retention(&page, reset));
+    }
+
+    #[test]
+    fn not_past_retention_with_partial_page() {
+        let reset = utc(2024, 1, 9, 17, 0, 0);
+        let mut page = full_page(reset - chrono::Duration::days(1), &[CRUCIBLE_ACTIVITY_MODE]);
+        page.pop();
+
+        assert!(!is_past_retention(&page, reset));
+    }
+
+    #[test]
+    fn past_cutoff_with_raid_on_page() {
+        let cutoff = utc(2024, 1, 9, 17, 0, 0);
+        let mut page = full_page(cutoff + chrono::Duration::hours(1), &[RAID_ACTIVITY_MODE]);
+        page.last_mut().unwrap().period = cutoff - chrono::Duration::seconds(1);
+
+        // Unlike the weekly reset, the cutoff prunes raids as well
+        assert!(is_past_cutoff(&page, cutoff));
+        assert!(!is_past_cutoff(&page[..page.len() - 1], cutoff));
+    }
+
+    fn ids(activities: &[CompletedActivity]) -> Vec<&str> {
+        activities.iter().map(|a| a.instance_id.as_str()).collect()
+    }
+
+    #[test]
+    fn filters_by_activity_type() {
+        let now = utc(2024, 1, 10, 12, 0, 0);
+        let activities = vec![
+            activity("raid", now, &[RAID_ACTIVITY_MODE]),
+            activity("dungeon", now, &[DUNGEON_ACTIVITY_MODE]),
+            activity("strike", now, &[STRIKE_ACTIVITY_MODE]),
+            activity("lostsector", now, &[LOSTSECTOR_ACTIVITY_MODE]),
+            activity("nightfall", now, &[NIGHTFALL_ACTIVITY_MODE]),
+            activity("crucible", now, &[CRUCIBLE_ACTIVITY_MODE]),
+            activity("trials", now, &[CRUCIBLE_ACTIVITY_MODE, TRIALS_ACTIVITY_MODE]),
+            activity("social", now, &[SOCIAL_ACTIVITY_MODE]),
+        ];
+
+        let all = FilterPreferences::default();
+        assert_eq!(
+            ids(&apply_filters(&activities, &all)),
+            ["raid", "dungeon", "strike", "lostsector", "nightfall", "crucible", "trials"]
+        );
+
+        let toggles: [(&str, fn(&mut FilterPreferences)); 7] = [
+            ("raid", |f| f.show_raids = false),
+            ("dungeon", |f| f.show_dungeons = false),
+            ("strike", |f| f.show_strikes = false),
+            ("lostsector", |f| f.show_lost_sectors = false),
+            ("nightfall", |f| f.show_nightfalls = false),
+            ("crucible", |f| f.show_crucible = false),
+            ("trials", |f| f.show_trials = false),
+        ];
+
+        for (hidden, hide) in toggles {
+            let mut filters = FilterPreferences::default();
+            hide(&mut filters);
+
+            let shown = apply_filters(&activities, &filters);
+            assert!(!ids(&shown).contains(&hidden), "{hidden} should be hidden");
+            assert_eq!(shown.len(), 6, "only {hidden} should be hidden");
+        }
+    }
+
+    #[test]
+    fn filters_by_specific_raid() {
+        let now = utc(2024, 1, 10, 12, 0, 0);
+        let mut wanted = activity("wanted", now, &[RAID_ACTIVITY_MODE]);
+        wanted.activity_hash = 10;
+        let mut other = activity("other", now, &[RAID_ACTIVITY_MODE]);
+        other.activity_hash = 20;
+        let mut dungeon = activity("dungeon", now, &[DUNGEON_ACTIVITY_MODE]);
+        dungeon.activity_hash = 30;
+
+        let mut filters = FilterPreferences::default();
+        filters.specific_raids = HashMap::from([(10, true), (20, false)]);
+
+        assert_eq!(
+            ids(&apply_filters(&[wanted, other, dungeon], &filters)),
+            ["wanted", "dungeon"]
+        );
+    }
+
+    #[test]
+    fn filters_by_fresh_start() {
+        let now = utc(2024, 1, 10, 12, 0, 0);
+        let mut fresh = activity("fresh", now, &[RAID_ACTIVITY_MODE]);
+        fresh.activity_was_started_from_beginning = Some(true);
+        let mut checkpoint = activity("checkpoint", now, &[RAID_ACTIVITY_MODE]);
+        checkpoint.activity_was_started_from_beginning = Some(false);
+        let unfetched = activity("unfetched", now, &[RAID_ACTIVITY_MODE]);
+        let activities = [fresh, checkpoint, unfetched];
+
+        let mut filters = FilterPreferences::default();
+        filters.show_checkpoint = false;
+        assert_eq!(ids(&apply_filters(&activities, &filters)), ["fresh", "unfetched"]);
+
+        let mut filters = FilterPreferences::default();
+        filters.show_fresh_start = false;
+        assert_eq!(ids(&apply_filters(&activities, &filters)), ["checkpoint", "unfetched"]);
+    }
+
+    #[test]
+    fn filters_by_flawless() {
+        let now = utc(2024, 1, 10, 12, 0, 0);
+        let mut flawless = activity("flawless", now, &[DUNGEON_ACTIVITY_MODE]);
+        flawless.flawless = true;
+        let deaths = activity("deaths", now, &[DUNGEON_ACTIVITY_MODE]);
+
+        let mut filters = FilterPreferences::default();
+        filters.show_flawless_only = true;
+
+        assert_eq!(ids(&apply_filters(&[flawless, deaths], &filters)), ["flawless"]);
+    }
+
+    #[test]
+    fn filters_by_completion() {
+        let now = utc(2024, 1, 10, 12, 0, 0);
+        let completed = activity("completed", now, &[RAID_ACTIVITY_MODE]);
+        let mut incomplete = activity("incomplete", now, &[RAID_ACTIVITY_MODE]);
+        incomplete.completed = false;
+        let activities = [completed, incomplete];
+
+        let mut filters = FilterPreferences::default();
+        filters.show_completed = false;
+        assert_eq!(ids(&apply_filters(&activities, &filters)), ["incomplete"]);
+
+        let mut filters = FilterPreferences::default();
+        filters.show_incomplete = false;
+        assert_eq!(ids(&apply_filters(&activities, &filters)), ["completed"]);
+    }
+
+    #[test]
+    fn filters_by_fireteam_size() {
+        let now = utc(2024, 1, 10, 12, 0, 0);
+        let activities: Vec<_> = [Some(1), Some(3), Some(6), None]
+            .into_iter()
+            .enumerate()
+            .map(|(i, size)| {
+                let mut a = activity(&i.to_string(), now, &[RAID_ACTIVITY_MODE]);
+                a.fireteam_size = size;
+                a
+            })
+            .collect();
+
+        let mut filters = FilterPreferences::default();
+        filters.min_fireteam_size = Some(2);
+        filters.max_fireteam_size = Some(3);
+
+        // Sizes aren't known until the PGCR is fetched, so those runs pass
+        assert_eq!(ids(&apply_filters(&activities, &filters)), ["1", "3"]);
+    }
+
+    #[test]
+    fn filters_by_duration() {
+        let now = utc(2024, 1, 10, 12, 0, 0);
+        let activities: Vec<_> = [600, 1800, 3600]
+            .into_iter()
+            .map(|seconds| {
+                let mut a = activity(&seconds.to_string(), now, &[RAID_ACTIVITY_MODE]);
+                a.activity_duration_seconds = seconds;
+                a
+            })
+            .collect();
+
+        let mut filters = FilterPreferences::default();
+        filters.min_duration_seconds = Some(1800);
+        assert_eq!(ids(&apply_filters(&activities, &filters)), ["1800", "3600"]);
+
+        let mut filters = FilterPreferences::default();
+        filters.max_duration_seconds = Some(1800);
+        assert_eq!(ids(&apply_filters(&activities, &filters)), ["600", "1800"]);
+    }
+
+    #[test]
+    fn filters_combine() {
+        let now = utc(2024, 1, 10, 12, 0, 0);
+        let raid = |id: &str, fresh: bool, completed: bool, seconds: usize| {
+            let mut a = activity(id, now, &[RAID_ACTIVITY_MODE]);
+            a.activity_was_started_from_beginning = Some(fresh);
+            a.completed = completed;
+            a.activity_duration_seconds = seconds;
+            a.fireteam_size = Some(6);
+            a
+        };
+        let activities = [
+            raid("match", true, true, 3000),
+            raid("checkpoint", false, true, 3000),
+            raid("incomplete", true, false, 3000),
+            raid("short", true, true, 300),
+            activity("dungeon", now, &[DUNGEON_ACTIVITY_MODE]),
+        ];
+
+        let mut filters = FilterPreferences::default();
+        filters.show_dungeons = false;
+        filters.show_checkpoint = false;
+        filters.show_incomplete = false;
+        filters.min_duration_seconds = Some(600);
+        filters.min_fireteam_size = Some(6);
+
+        assert_eq!(ids(&apply_filters(&activities, &filters)), ["match"]);
+    }
+
+    #[test]
+    fn shared_instance_counted_and_returned_once() {
+        let now = utc(2024, 1, 10, 20, 0, 0);
+        // The same run is listed under each of three characters, e.g. after swapping mid-run
+        let histories: Vec<_> = (0..3)
+            .map(|_| activity("shared", now - chrono::Duration::hours(1), &[RAID_ACTIVITY_MODE]))
+            .collect();
+
+        let mut fetched = histories.clone();
+        dedup_activities(&mut fetched);
+        let stats = compute_history_stats(&fetched, now, &HashSet::new(), &HashMap::new());
+
+        assert_eq!(ids(&fetched), ["shared"]);
+        assert_eq!(stats.daily_clears, 1);
+        assert_eq!(stats.weekly_clears, 1);
+        assert_eq!(stats.season_clears.values().sum::<u32>(), 1);
+
+        let mut cache_manager = crate::cache::CacheManager::new();
+        cache_manager.update_cache("profile".to_string(), histories.clone());
+        cache_manager.merge_activities("profile".to_string(), histories);
+
+        let cached = &cache_manager.get_cached_activities("profile").unwrap().activities;
+        assert_eq!(ids(cached), ["shared"]);
+    }
+
+    fn first_clears(cache_manager: &crate::cache::CacheManager, new: &[CompletedActivity]) -> Vec<bool> {
+        // Hashes 1 and 2 are the original and a reissue of the same raid
+        let families = HashMap::from([(1, 1), (2, 1)]);
+
+        find_new_clears(cache_manager.get_cached_activities("profile"), new, &HashSet::new(), &families)
+            .iter()
+            .map(|c| c.first_clear)
+            .collect()
+    }
+
+    #[test]
+    fn first_clear_of_new_family() {
+        let now = utc(2024, 1, 10, 20, 0, 0);
+        let mut cache_manager = crate::cache::CacheManager::new();
+        cache_manager.update_cache("profile".to_string(), vec![activity("old", now, &[RAID_ACTIVITY_MODE])]);
+
+        let mut new = activity("new", now + chrono::Duration::hours(1), &[RAID_ACTIVITY_MODE]);
+        new.activity_hash = 3;
+
+        assert_eq!(first_clears(&cache_manager, &[new]), [true]);
+    }
+
+    #[test]
+    fn reissue_is_not_a_first_clear() {
+        let now = utc(2024, 1, 10, 20, 0, 0);
+        let mut cache_manager = crate::cache::CacheManager::new();
+        cache_manager.update_cache("profile".to_string(), vec![activity("old", now, &[RAID_ACTIVITY_MODE])]);
+
+        let mut new = activity("new", now + chrono::Duration::hours(1), &[RAID_ACTIVITY_MODE]);
+        new.activity_hash = 2;
+
+        assert_eq!(first_clears(&cache_manager, &[new]), [false]);
+    }
+
+    #[test]
+    fn no_first_clear_on_trimmed_cache() {
+        let now = utc(2024, 1, 10, 20, 0, 0);
+        let mut cache_manager = crate::cache::CacheManager::new();
+        cache_manager.update_cache(
+            "profile".to_string(),
+            vec![
+                activity("kept", now, &[RAID_ACTIVITY_MODE]),
+                activity("pruned", now - chrono::Duration::days(60), &[RAID_ACTIVITY_MODE]),
+            ],
+        );
+        cache_manager.prune_activities_before("profile", now - chrono::Duration::days(30));
+
+        let mut new = activity("new", now + chrono::Duration::hours(1), &[RAID_ACTIVITY_MODE]);
+        new.activity_hash = 3;
+
+        assert_eq!(first_clears(&cache_manager, &[new]), [false]);
+    }
+
+    #[test]
+    fn streak_over_consecutive_days() {
+        let now = utc(2024, 1, 12, 20, 0, 0);
+        let clears = [
+            utc(2024, 1, 10, 18, 0, 0),
+            utc(2024, 1, 11, 18, 0, 0),
+            // Several clears in one day count once
+            utc(2024, 1, 12, 18, 0, 0),
+            utc(2024, 1, 12, 19, 0, 0),
+        ];
+
+        assert_eq!(compute_streaks(clears.into_iter(), now), (3, 3));
+    }
+
+    #[test]
+    fn streak_broken_by_gap() {
+        let now = utc(2024, 1, 12, 20, 0, 0);
+        let clears = [
+            utc(2024, 1, 5, 18, 0, 0),
+            utc(2024, 1, 6, 18, 0, 0),
+            utc(2024, 1, 7, 18, 0, 0),
+            // No clear on the 8th or 9th
+            utc(2024, 1, 10, 18, 0, 0),
+            utc(2024, 1, 11, 18, 0, 0),
+        ];
+
+        // Today has no clear yet, which doesn't end the current streak
+        assert_eq!(compute_streaks(clears.into_iter(), now), (2, 3));
+
+        // A whole day without one does
+        let tomorrow = now + chrono::Duration::days(1);
+        assert_eq!(compute_streaks(clears.into_iter(), tomorrow), (0, 3));
+    }
+
+    #[test]
+    fn streak_split_at_daily_reset() {
+        let now = utc(2024, 1, 11, 20, 0, 0);
+
+        // Either side of the same midnight but within one Destiny day
+        let same_day = [utc(2024, 1, 10, 23, 0, 0), utc(2024, 1, 11, 16, 59, 59)];
+        assert_eq!(compute_streaks(same_day.into_iter(), now), (1, 1));
+
+        // A second apart, but either side of the reset
+        let either_side = [utc(2024, 1, 11, 16, 59, 59), utc(2024, 1, 11, 17, 0, 0)];
+        assert_eq!(compute_streaks(either_side.into_iter(), now), (2, 2));
+    }
+
+    #[test]
+    fn clear_stats_without_durations() {
+        assert!(ClearStats::from_durations(Vec::new()).is_none());
+        assert!(compute_clear_stats(&[], &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn clear_stats_median_of_odd_count() {
+        let stats = ClearStats::from_durations(vec![1500, 900, 1200]).unwrap();
+
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.median_secs, 1200);
+        assert_eq!(stats.average_secs, 1200);
+        assert_eq!(stats.best_secs, 900);
+    }
+
+    #[test]
+    fn clear_stats_median_of_even_count() {
+        let stats = ClearStats::from_durations(vec![1800, 900, 1000, 1200]).unwrap();
+
+        // Mean of the two middle durations, 1000 and 1200
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.median_secs, 1100);
+        assert_eq!(stats.average_secs, 1225);
+        assert_eq!(stats.best_secs, 900);
+    }
+
+    fn history_page(page: usize) -> Vec<CompletedActivity> {
+        let newest = utc(2024, 1, 10, 12, 0, 0) - chrono::Duration::days(page as i64);
+
+        (0..ACTIVITY_HISTORY_PAGE_SIZE)
+            .map(|i| activity(&format!("{page}-{i}"), newest - chrono::Duration::minutes(i as i64), &[RAID_ACTIVITY_MODE]))
+            .collect()
+    }
+
+    fn network_error() -> ApiError {
+        ApiError::ResponseError(BungieResponseError::NetworkError(anyhow::anyhow!("connection reset")))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn walk_retries_transient_failure_mid_paging() {
+        const PAGES: usize = 8;
+        let failed = Arc::new(AtomicBool::new(false));
+
+        let fetch_page = move |page: usize| {
+            let failed = failed.clone();
+
+            async move {
+                if page == 3 && !failed.swap(true, Ordering::Relaxed) {
+                    return Err(network_error());
+                }
+
+                Ok(Some(if page < PAGES { history_page(page) } else { Vec::new() }))
+            }
+        };
+
+        let walked = walk_history_pages(fetch_page, |_| false).await.unwrap();
+
+        let walked: HashSet<String> = walked.into_iter().map(|a| a.instance_id).collect();
+        let expected: HashSet<String> = (0..PAGES)
+            .flat_map(history_page)
+            .map(|a| a.instance_id)
+            .collect();
+
+        assert_eq!(walked, expected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn walk_fails_once_retries_run_out() {
+        let fetch_page = |page: usize| async move {
+            match page {
+                3 => Err(network_error()),
+                _ => Ok(Some(history_page(page))),
+            }
+        };
+
+        assert!(walk_history_pages(fetch_page, |_| false).await.is_err());
     }
-    
-    let days_since_tuesday = (reset_time.weekday().num_days_from_monday() + 5) % 7;
-    reset_time - chrono::Duration::days(days_since_tuesday as i64)
 }
@@ -0,0 +1,118 @@
+use std::{io, net::Ipv4Addr, sync::Arc};
+
+use tauri::async_runtime::{self, JoinHandle};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+};
+use tracing::info;
+
+use crate::{consts::LOCAL_SERVER_MAX_REQUEST_BYTES, pollers::playerdata::PlayerDataStatus};
+
+const OVERLAY_PAGE: &str = include_str!("server/overlay.html");
+
+/// Serves the latest player data on localhost, e.g. for OBS browser sources. Sent without CORS
+/// headers, so the bundled page can read it but other sites open in a browser can't.
+pub struct LocalServer {
+    task: JoinHandle<()>,
+    /// The port from preferences, which may differ from `port` if it was taken
+    pub configured_port: u16,
+    pub port: u16,
+}
+
+impl LocalServer {
+    pub async fn start(status: Arc<Mutex<PlayerDataStatus>>, configured_port: u16) -> io::Result<Self> {
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, configured_port)).await {
+            Ok(l) => l,
+            Err(_) => TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?,
+        };
+
+        let port = listener.local_addr()?.port();
+
+        info!(target: "server", "🌐 Local server: Listening on http://127.0.0.1:{}", port);
+
+        let task = async_runtime::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((s, _)) => s,
+                    Err(_) => continue,
+                };
+
+                let status = status.clone();
+
+                async_runtime::spawn(async move {
+                    let _ = handle_connection(stream, status).await;
+                });
+            }
+        });
+
+        Ok(Self {
+            task,
+            configured_port,
+            port,
+        })
+    }
+
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    status: Arc<Mutex<PlayerDataStatus>>,
+) -> io::Result<()> {
+    let mut buf = vec![0; LOCAL_SERVER_MAX_REQUEST_BYTES];
+    let mut len = 0;
+
+    // Only the request line matters, so stop reading once the headers are in
+    while !buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+        if len == buf.len() {
+            return write_response(&mut stream, "413 Payload Too Large", "text/plain", "").await;
+        }
+
+        match stream.read(&mut buf[len..]).await? {
+            0 => return Ok(()),
+            n => len += n,
+        }
+    }
+
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+
+    // A query string (e.g. a cache-busting one) doesn't change what's served
+    let (method, path) = (parts.next(), parts.next().and_then(|p| p.split('?').next()));
+
+    match (method, path) {
+        (Some("GET"), Some("/playerdata")) => {
+            let body = serde_json::to_string(&*status.lock().await)?;
+            write_response(&mut stream, "200 OK", "application/json", &body).await
+        }
+        (Some("GET"), Some("/")) => {
+            write_response(&mut stream, "200 OK", "text/html; charset=utf-8", OVERLAY_PAGE).await
+        }
+        (Some("GET"), _) => write_response(&mut stream, "404 Not Found", "text/plain", "").await,
+        _ => write_response(&mut stream, "405 Method Not Allowed", "text/plain", "").await,
+    }
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Connection: close\r\n\r\n\
+         {body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
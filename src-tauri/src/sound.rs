@@ -0,0 +1,84 @@
+use std::path::Path;
+
+/// Built-in sounds selectable by name, mapped to the Windows sound scheme aliases
+const BUILT_IN_SOUNDS: [(&str, &str); 4] = [
+    ("default", "SystemDefault"),
+    ("notification", "SystemNotification"),
+    ("asterisk", "SystemAsterisk"),
+    ("exclamation", "SystemExclamation"),
+];
+
+/// Plays `sound` without blocking, either a built-in name or a path to an audio file.
+/// A missing file or unknown name is skipped, since a silent clear beats a failed one.
+pub fn play_notification_sound(sound: &str) {
+    let sound = sound.trim();
+
+    let built_in = BUILT_IN_SOUNDS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(sound));
+
+    if let Some((_, alias)) = built_in {
+        platform::play_alias(alias);
+    } else if Path::new(sound).is_file() {
+        platform::play_file(Path::new(sound));
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::path::Path;
+
+    use widestring::U16CString;
+    use windows::{
+        core::PCWSTR,
+        Win32::{
+            Foundation::HMODULE,
+            Media::Audio::{PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_FLAGS, SND_NODEFAULT},
+        },
+    };
+
+    pub fn play_alias(alias: &str) {
+        play(alias, SND_ALIAS);
+    }
+
+    pub fn play_file(path: &Path) {
+        play(&path.to_string_lossy(), SND_FILENAME);
+    }
+
+    fn play(sound: &str, kind: SND_FLAGS) {
+        let Ok(sound) = U16CString::from_str(sound) else {
+            return;
+        };
+
+        // SND_ASYNC copies what it needs, so the string can be dropped as soon as this returns
+        unsafe {
+            PlaySoundW(
+                PCWSTR(sound.as_ptr()),
+                HMODULE::default(),
+                kind | SND_ASYNC | SND_NODEFAULT,
+            );
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use std::{path::Path, process::Command};
+
+    #[cfg(target_os = "macos")]
+    const PLAYER: &str = "afplay";
+    #[cfg(not(target_os = "macos"))]
+    const PLAYER: &str = "paplay";
+
+    /// Sound scheme aliases only exist on Windows
+    pub fn play_alias(_alias: &str) {}
+
+    pub fn play_file(path: &Path) {
+        let path = path.to_path_buf();
+
+        // Waited on off-thread so the player is reaped without holding up the poller
+        std::thread::spawn(move || {
+            let _ = Command::new(PLAYER).arg(path).status();
+        });
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Utc};
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde::Serialize;
+use tauri::async_runtime;
+use tracing::warn;
+
+use crate::{
+    api::responses::CompletedActivity,
+    consts::{USER_AGENT, WEBHOOK_MAX_ATTEMPTS, WEBHOOK_RETRY_DELAY, WEBHOOK_TIMEOUT},
+    format::format_duration,
+};
+
+static WEBHOOK_CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+});
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearPayload {
+    pub instance_id: String,
+    pub activity_hash: usize,
+    pub activity_name: Option<String>,
+    pub period: DateTime<Utc>,
+    pub activity_duration: String,
+    pub activity_duration_seconds: usize,
+    pub completed: bool,
+}
+
+impl ClearPayload {
+    pub fn new(
+        activity: &CompletedActivity,
+        activity_name: Option<String>,
+        display_milliseconds: bool,
+    ) -> Self {
+        Self {
+            instance_id: activity.instance_id.clone(),
+            activity_hash: activity.activity_hash,
+            activity_name,
+            period: activity.period,
+            activity_duration: format_duration(
+                activity.activity_duration_seconds as i64 * 1000,
+                display_milliseconds,
+            ),
+            activity_duration_seconds: activity.activity_duration_seconds,
+            completed: activity.completed,
+        }
+    }
+}
+
+/// Posts each clear to `url` in the background. Delivery is best-effort: failed requests are
+/// retried a few times and then dropped.
+pub fn send_clears(url: String, clears: Vec<ClearPayload>) {
+    async_runtime::spawn(async move {
+        for clear in clears {
+            let delivered = post_with_retry(&url, &clear).await;
+
+            if !delivered {
+                warn!(target: "webhook", "⚠️ Webhook: Gave up delivering clear {}", clear.instance_id);
+            }
+        }
+    });
+}
+
+async fn post_with_retry(url: &str, clear: &ClearPayload) -> bool {
+    let body = match serde_json::to_vec(clear) {
+        Ok(b) => b,
+        Err(_) => return false,
+    };
+
+    for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+        let res = WEBHOOK_CLIENT
+            .post(url)
+            .header("User-Agent", USER_AGENT)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone())
+            .send()
+            .await;
+
+        match res {
+            Ok(r) if r.status().is_success() => return true,
+            Ok(r) => {
+                warn!(target: "webhook", "⚠️ Webhook: Attempt {attempt} returned {}", r.status());
+            }
+            // The webhook URL carries its token, so it's kept out of the log
+            Err(e) => {
+                let e = e.without_url();
+                warn!(target: "webhook", "⚠️ Webhook: Attempt {attempt} failed: {e}");
+            }
+        }
+
+        if attempt < WEBHOOK_MAX_ATTEMPTS {
+            tokio::time::sleep(WEBHOOK_RETRY_DELAY).await;
+        }
+    }
+
+    false
+}
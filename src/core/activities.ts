@@ -1,3 +1,4 @@
+import type { TrackableActivity } from "./types";
 
 export const KNOWN_RAIDS = {
     2122313384: "Last Wish",
@@ -43,15 +44,37 @@ export function getUniqueRaids(): Array<{ hash: number; name: string; allHashes:
     return Array.from(uniqueRaids.values());
 }
 
-export function getUniqueDungeons(): Array<{ hash: number; name: string }> {
-    const uniqueDungeons = new Map<string, { hash: number; name: string }>();
+export function getUniqueDungeons(): Array<{ hash: number; name: string; allHashes: number[] }> {
+    const uniqueDungeons = new Map<string, { hash: number; name: string; allHashes: number[] }>();
 
     for (const [hash, name] of Object.entries(KNOWN_DUNGEONS)) {
         const hashNum = parseInt(hash);
-        if (!uniqueDungeons.has(name)) {
-            uniqueDungeons.set(name, { hash: hashNum, name });
+        if (uniqueDungeons.has(name)) {
+            uniqueDungeons.get(name)!.allHashes.push(hashNum);
+        } else {
+            uniqueDungeons.set(name, { hash: hashNum, name, allHashes: [hashNum] });
         }
     }
 
     return Array.from(uniqueDungeons.values());
 }
+
+// Adds activities the backend has definitions for, e.g. ones released after this list
+export function mergeTrackableActivities(
+    known: Array<{ hash: number; name: string; allHashes: number[] }>,
+    trackable: TrackableActivity[]
+): Array<{ hash: number; name: string; allHashes: number[] }> {
+    const merged = new Map(known.map(a => [a.name, { ...a, allHashes: [...a.allHashes] }]));
+
+    for (const activity of trackable) {
+        const existing = merged.get(activity.name);
+
+        if (existing) {
+            existing.allHashes = Array.from(new Set([...existing.allHashes, ...activity.hashes]));
+        } else {
+            merged.set(activity.name, { hash: activity.hashes[0], name: activity.name, allHashes: activity.hashes });
+        }
+    }
+
+    return Array.from(merged.values());
+}
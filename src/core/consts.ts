@@ -6,5 +6,8 @@ export const ACTIVITY_TYPES = {
     4: "Raid",
     82: "Dungeon",
     18: "Strike",
-    87: "Lost Sector"
+    87: "Lost Sector",
+    46: "Nightfall",
+    5: "Crucible",
+    84: "Trials"
 }
@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/tauri";
-import type { ActivityInfo, BungieProfile, PlayerDataStatus, Preferences, Profile, ProfileInfo, Profiles } from "./types";
+import type { ActivityInfo, BungieProfile, CacheStats, CareerStats, Character, CompletedActivity, PlayerDataStatus, PostGameCarnageReport, Preferences, Profile, ProfileInfo, Profiles, TrackableActivity } from "./types";
 
 export function openPreferences(): Promise<void> {
     return invoke("open_preferences");
@@ -17,28 +17,116 @@ export function setPreferences(preferences: Preferences): Promise<void> {
     return invoke("set_preferences", { preferences });
 }
 
+export function saveView(name: string): Promise<Preferences> {
+    return invoke("save_view", { name });
+}
+
+export function deleteView(name: string): Promise<Preferences> {
+    return invoke("delete_view", { name });
+}
+
+export function applyView(name: string): Promise<Preferences> {
+    return invoke("apply_view", { name });
+}
+
 export function getProfiles(): Promise<Profiles> {
     return invoke("get_profiles");
 }
 
-export function setProfiles(profiles: Profiles): Promise<void> {
+export function setProfiles(profiles: Profiles): Promise<Profiles> {
     return invoke("set_profiles", { profiles });
 }
 
+export function signIn(): Promise<Profiles> {
+    return invoke("sign_in");
+}
+
+export function signOut(): Promise<void> {
+    return invoke("sign_out");
+}
+
+export function isSignedIn(): Promise<boolean> {
+    return invoke("is_signed_in");
+}
+
 export function getProfileInfo(profile: Profile): Promise<ProfileInfo> {
     return invoke("get_profile_info", { profile });
 }
 
+export function resolveProfile(profile: Profile): Promise<Profile> {
+    return invoke("resolve_profile", { profile });
+}
+
+export function getCareerStats(profile: Profile): Promise<CareerStats> {
+    return invoke("get_career_stats", { profile });
+}
+
+export function getCharacters(profile: Profile): Promise<Character[]> {
+    return invoke("get_characters", { profile });
+}
+
 export function getActivityInfo(activityHash: number): Promise<ActivityInfo> {
     return invoke("get_activity_info", { activityHash });
 }
 
+export function getActivityDetails(instanceId: string): Promise<PostGameCarnageReport> {
+    return invoke("get_activity_details", { instanceId });
+}
+
+export function getTrackableActivities(): Promise<TrackableActivity[]> {
+    return invoke("get_trackable_activities");
+}
+
 export function searchProfile(displayName: string, displayNameCode: number): Promise<BungieProfile[]> {
     return invoke("search_profile", { displayName, displayNameCode });
 }
 
-export function getPlayerdata(): Promise<PlayerDataStatus | null> {
+export function searchProfileLegacy(displayName: string, platform: number): Promise<BungieProfile[]> {
+    return invoke("search_profile_legacy", { displayName, platform });
+}
+
+export function getPlayerdata(): Promise<PlayerDataStatus> {
     return invoke("get_playerdata");
 }
 
+export function getPersonalBests(): Promise<Record<number, CompletedActivity>> {
+    return invoke("get_personal_bests");
+}
+
+export function refreshCache(): Promise<void> {
+    return invoke("refresh_cache");
+}
+
+export function checkNow(): Promise<void> {
+    return invoke("check_now");
+}
+
+export function cancelRebuild(): Promise<boolean> {
+    return invoke("cancel_rebuild");
+}
+
+export function getCacheStats(): Promise<CacheStats> {
+    return invoke("get_cache_stats");
+}
+
+export function getLocalServerPort(): Promise<number | null> {
+    return invoke("get_local_server_port");
+}
+
+export function copyRunSummary(): Promise<string> {
+    return invoke("copy_run_summary");
+}
+
+export function clearAllData(): Promise<void> {
+    return invoke("clear_all_data");
+}
+
+export function exportCache(path: string): Promise<void> {
+    return invoke("export_cache", { path });
+}
+
+export function importCache(path: string, replace: boolean): Promise<number> {
+    return invoke("import_cache", { path, replace });
+}
+
 export { invoke };
@@ -13,6 +13,7 @@ export type BungieProfile = {
 export type Profiles = {
     savedProfiles: Profile[],
     selectedProfile: Profile,
+    trackedProfiles: Profile[],
 }
 
 export type Profile = {
@@ -27,6 +28,62 @@ export type ProfileInfo = {
     characterIds: string[];
 };
 
+export type ProfileCacheStats = {
+    activityCount: number;
+    lastUpdated: string;
+};
+
+export type CacheStats = {
+    profiles: { [profileId: string]: ProfileCacheStats };
+    diskSizeBytes: number;
+};
+
+export type CareerStats = {
+    raidClears: number;
+    dungeonClears: number;
+    fastestRaidClearSeconds: number | null;
+    fastestDungeonClearSeconds: number | null;
+    kills: number;
+    deaths: number;
+};
+
+export type Character = {
+    characterId: string;
+    class: 'titan' | 'hunter' | 'warlock' | 'unknown';
+    light: number;
+    emblemPath: string | null;
+    emblemBackgroundPath: string | null;
+    dateLastPlayed: string;
+};
+
+export type PgcrPlayer = {
+    membershipId: string;
+    characterId: string;
+    displayName: string;
+    class: Character["class"];
+    light: number;
+    completed: boolean;
+    kills: number;
+    deaths: number;
+    assists: number;
+    timePlayedSeconds: number;
+};
+
+export type PostGameCarnageReport = {
+    instanceId: string;
+    activityHash: number;
+    period: string;
+    completed: boolean;
+    activityDurationSeconds: number;
+    activityWasStartedFromBeginning: boolean | null;
+    startingPhaseIndex: number | null;
+    fireteamSize: number | null;
+    kills: number | null;
+    deaths: number | null;
+    assists: number | null;
+    players: PgcrPlayer[];
+};
+
 export type ColorPreferences = {
     completedDotColor: string;
     incompleteDotColor: string;
@@ -41,12 +98,19 @@ export type FilterPreferences = {
     showDungeons: boolean;
     showStrikes: boolean;
     showLostSectors: boolean;
+    showNightfalls: boolean;
+    showCrucible: boolean;
+    showTrials: boolean;
     showCompleted: boolean;
     showIncomplete: boolean;
     showFreshStart: boolean;
     showCheckpoint: boolean;
+    showFlawlessOnly: boolean;
+    currentSeasonOnly: boolean;
     minDurationSeconds: number | null;
     maxDurationSeconds: number | null;
+    minFireteamSize: number | null;
+    maxFireteamSize: number | null;
     specificRaids: {
         [activityHash: number]: boolean;
     };
@@ -61,37 +125,134 @@ export type SortPreferences = {
     timeRange: 'all' | 'today' | 'week' | 'month';
 };
 
+export type OverlayPosition =
+    | { type: 'topLeft' }
+    | { type: 'topRight' }
+    | { type: 'bottomLeft' }
+    | { type: 'bottomRight' }
+    | { type: 'custom', x: number, y: number };
+
+export type WidgetKind = 'timer' | 'dailyClears' | 'weeklyClears' | 'personalBest' | 'streak' | 'resetCountdown' | 'fireteam';
+
 export type Preferences = {
     enableOverlay: boolean;
-    displayDailyClears: boolean;
+    overlayWidgets: WidgetKind[];
     displayClearNotifications: boolean;
+    notificationSound: string | null;
     displayMilliseconds: boolean;
+    pollIntervalSeconds: number;
+    historyCheckMultiplier: number;
+    slowPollWhenGameClosed: boolean;
+    overlayPosition: OverlayPosition;
+    compactOverlay: boolean;
+    overlayMonitorIndex: number | null;
+    overlayScale: number;
+    toggleOverlayHotkey: string | null;
+    enableDiscordPresence: boolean;
+    clearWebhookUrl: string | null;
+    fetchConcurrency: number;
+    pgcrConcurrency: number;
+    requestsPerSecond: number;
+    historyRetentionDays: number | null;
+    enableLocalServer: boolean;
+    localServerPort: number;
+    logLevel: string | null;
     colors: ColorPreferences;
     filters: FilterPreferences;
     sorting: SortPreferences;
+    views: Record<string, [FilterPreferences, SortPreferences]>;
+};
+
+export type ActivityTick = {
+    activityHash: number;
+    elapsedMillis: number;
+};
+
+export type OverlayTick = {
+    activity: ActivityTick | null;
+    millisUntilDailyReset: number;
+    millisUntilWeeklyReset: number;
+};
+
+export type RebuildProgress = {
+    fetched: number;
+    failed: number;
+    total: number;
+    cancelled: boolean;
+};
+
+export type AppError = {
+    type: 'network' | 'throttled' | 'profilePrivate' | 'serviceDisabled' | 'notFound' | 'bungie' | 'server' | 'internal';
+    source: 'network' | 'bungie' | 'app';
+    message: string;
+    seconds?: number;
+    errorCode?: number;
+    statusCode?: number;
 };
 
 export type PlayerDataStatus = {
     lastUpdate?: PlayerData | null,
-    error?: string | null,
+    error?: AppError | null,
+    throttledUntil?: string | null,
 }
 
 export type PlayerData = {
     currentActivity: CurrentActivity;
     activityHistory: CompletedActivity[];
+    personalBests: Record<number, CompletedActivity>;
+    clearStats: Record<number, ClearStats>;
+    dailyClears: number;
+    weeklyClears: number;
+    trackedDailyClears: number;
+    trackedWeeklyClears: number;
+    nextDailyReset: string;
+    nextWeeklyReset: string;
+    currentStreak: number;
+    bestStreak: number;
+    /** Clears per season number, 0 for clears before the first known season */
+    seasonClears: Record<number, number>;
+    currentSeasonStart: string | null;
     profileInfo: ProfileInfo;
 };
 
+export type ClearStats = {
+    count: number;
+    averageSecs: number;
+    medianSecs: number;
+    bestSecs: number;
+};
+
 export type CurrentActivity = {
     startDate: string;
     activityHash: number;
     activityInfo: ActivityInfo;
+    fireteam: FireteamMember[];
+    state: 'offline' | 'orbit' | 'inActivity';
+    phase?: ActivityPhase;
+};
+
+export type ActivityPhase = {
+    current: number;
+    total: number;
+};
+
+export type TrackableActivity = {
+    name: string;
+    isRaid: boolean;
+    hashes: number[];
+};
+
+export type FireteamMember = {
+    membershipId: string;
+    displayName: string;
 };
 
 export type ActivityInfo = {
     name: string;
     activityModes: number[];
     backgroundImage: string;
+    destination?: string | null;
+    place?: string | null;
 };
 
 export type CompletedActivity = {
@@ -105,4 +266,14 @@ export type CompletedActivity = {
     completionReason: number;
     startingPhaseIndex?: number;
     activityWasStartedFromBeginning?: boolean;
+    fireteamSize?: number | null;
+    kills?: number | null;
+    deaths?: number | null;
+    assists?: number | null;
+    flawless?: boolean;
+    pgcrVersion?: number;
+};
+
+export type NewClear = CompletedActivity & {
+    firstClear: boolean;
 };
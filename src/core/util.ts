@@ -1,6 +1,6 @@
 import { ACTIVITY_TYPES } from "./consts";
 import { KNOWN_RAIDS, KNOWN_DUNGEONS } from "./activities";
-import type { ActivityInfo, CompletedActivity, FilterPreferences, SortPreferences } from "./types";
+import type { ActivityInfo, CompletedActivity, FilterPreferences, SortPreferences, WidgetKind } from "./types";
 
 // Pre-build reverse lookup: raid name -> set of hashes with that name
 const RAID_NAME_TO_HASHES: { [name: string]: Set<number> } = {};
@@ -31,19 +31,6 @@ export function getDestinyWeeklyResetTime(date: Date = new Date()): Date {
     return resetTime;
 }
 
-export function countDailyClears(activityHistory: CompletedActivity[]): number {
-    const dailyResetTime = getDestinyResetTime();
-    let clearCount = 0;
-
-    for (let activity of activityHistory) {
-        if (activity.completed && new Date(activity.period) >= dailyResetTime) {
-            clearCount++;
-        }
-    }
-
-    return clearCount;
-}
-
 export function formatTime(millis: number): string {
     let seconds = Math.floor(millis / 1000);
 
@@ -60,6 +47,10 @@ export function formatMillis(millis: number): string {
     return ":" + String(millis % 1000).padStart(3, "0").substring(0, 2);
 }
 
+export function secondsUntil(date: string): number {
+    return Math.max(0, Math.ceil((new Date(date).getTime() - Date.now()) / 1000));
+}
+
 export function countClears(activityHistory: CompletedActivity[]): number {
     let clearCount = 0;
     for (let activity of activityHistory) {
@@ -76,6 +67,11 @@ export function determineActivityType(modes: number[]): string | undefined {
         return;
     }
 
+    // Trials games also carry the Crucible mode
+    if (modes.includes(84)) {
+        return ACTIVITY_TYPES[84];
+    }
+
     for (const mode of modes) {
         if (ACTIVITY_TYPES[mode]) {
             return ACTIVITY_TYPES[mode];
@@ -86,8 +82,11 @@ export function determineActivityType(modes: number[]): string | undefined {
 export function filterActivities(
     activities: CompletedActivity[],
     filters: FilterPreferences,
-    activityInfoMap: { [hash: number]: ActivityInfo }
+    activityInfoMap: { [hash: number]: ActivityInfo },
+    currentSeasonStart: string | null = null
 ): CompletedActivity[] {
+    const seasonStart = filters.currentSeasonOnly && currentSeasonStart ? new Date(currentSeasonStart) : null;
+
     return activities.filter(activity => {
         const activityType = determineActivityType(activity.modes);
         let typeMatch = false;
@@ -135,12 +134,23 @@ export function filterActivities(
             case "Lost Sector":
                 typeMatch = filters.showLostSectors;
                 break;
+            case "Nightfall":
+                typeMatch = filters.showNightfalls;
+                break;
+            case "Crucible":
+                typeMatch = filters.showCrucible;
+                break;
+            case "Trials":
+                typeMatch = filters.showTrials;
+                break;
             default:
                 typeMatch = false;
         }
 
         if (!typeMatch) return false;
 
+        if (seasonStart && new Date(activity.period) < seasonStart) return false;
+
         // Apply checkpoint filtering (independent of completed/incomplete)
         if (activity.activityWasStartedFromBeginning !== undefined && activity.activityWasStartedFromBeginning !== null) {
             // activityWasStartedFromBeginning: true = fresh start, false = checkpoint
@@ -153,10 +163,18 @@ export function filterActivities(
             if (!isFreshStart && !filters.showCheckpoint) return false;
         }
 
+        if (filters.showFlawlessOnly && !activity.flawless) return false;
+
         // Then apply completed/incomplete filtering
         if (activity.completed && !filters.showCompleted) return false;
         if (!activity.completed && !filters.showIncomplete) return false;
 
+        // Unknown fireteam sizes pass, their PGCR may not have been fetched yet
+        if (activity.fireteamSize !== null && activity.fireteamSize !== undefined) {
+            if (filters.minFireteamSize && activity.fireteamSize < filters.minFireteamSize) return false;
+            if (filters.maxFireteamSize && activity.fireteamSize > filters.maxFireteamSize) return false;
+        }
+
         if (filters.minDurationSeconds !== null && filters.minDurationSeconds !== undefined) {
             if (activity.activityDurationSeconds < filters.minDurationSeconds) {
                 return false;
@@ -226,9 +244,27 @@ export function sortActivities(
 export function getDefaultPreferences() {
     return {
         enableOverlay: false,
-        displayDailyClears: true,
+        overlayWidgets: ["timer", "dailyClears"] as WidgetKind[],
         displayClearNotifications: true,
+        notificationSound: null,
         displayMilliseconds: false,
+        pollIntervalSeconds: 5,
+        historyCheckMultiplier: 5,
+        slowPollWhenGameClosed: true,
+        overlayPosition: { type: "topLeft" as const },
+        compactOverlay: false,
+        overlayMonitorIndex: null,
+        overlayScale: 1,
+        toggleOverlayHotkey: null,
+        enableDiscordPresence: false,
+        clearWebhookUrl: null,
+        fetchConcurrency: 30,
+        pgcrConcurrency: 75,
+        requestsPerSecond: 25,
+        historyRetentionDays: null,
+        enableLocalServer: false,
+        localServerPort: 7727,
+        logLevel: null,
         colors: {
             completedDotColor: "#33ee33",
             incompleteDotColor: "#ee3333",
@@ -242,12 +278,19 @@ export function getDefaultPreferences() {
             showDungeons: true,
             showStrikes: true,
             showLostSectors: true,
+            showNightfalls: true,
+            showCrucible: true,
+            showTrials: true,
             showCompleted: true,
             showIncomplete: true,
             showFreshStart: true,
             showCheckpoint: true,
+            showFlawlessOnly: false,
+            currentSeasonOnly: false,
             minDurationSeconds: null,
             maxDurationSeconds: null,
+            minFireteamSize: null,
+            maxFireteamSize: null,
             specificRaids: {},
             specificDungeons: {}
         },
@@ -255,6 +298,7 @@ export function getDefaultPreferences() {
             sortBy: "time" as const,
             sortOrder: "desc" as const,
             timeRange: "all" as const
-        }
+        },
+        views: {}
     };
 }
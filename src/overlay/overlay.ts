@@ -2,19 +2,49 @@ import "../core/global.css"
 import "./overlay.css"
 import { appWindow } from "@tauri-apps/api/window";
 import { createPopup as _createPopup, type Popup } from "./popups";
-import type { TauriEvent, Preferences, CurrentActivity, PlayerDataStatus } from "../core/types";
-import { countDailyClears, determineActivityType, formatMillis, formatTime, getDefaultPreferences } from "../core/util";
+import type { TauriEvent, AppError, Preferences, PlayerData, WidgetKind, ActivityTick, OverlayTick, CompletedActivity, CurrentActivity, PlayerDataStatus, OverlayPosition } from "../core/types";
+import { determineActivityType, formatMillis, formatTime, getDefaultPreferences, secondsUntil } from "../core/util";
 import { getPlayerdata, getPreferences, getActivityInfo } from "../core/ipc";
 
 const widgetElem = document.querySelector<HTMLElement>("#widget")!;
 const widgetContentElem = document.querySelector<HTMLElement>("#widget-content")!;
 const timerElem = document.querySelector<HTMLElement>("#timer")!;
+const orbitElem = document.querySelector<HTMLElement>("#orbit")!;
 const timeElem = document.querySelector<HTMLElement>("#time")!;
 const msElem = document.querySelector<HTMLElement>("#ms")!;
+const phaseElem = document.querySelector<HTMLElement>("#phase")!;
 const counterElem = document.querySelector<HTMLElement>("#counter")!;
 const dailyElem = document.querySelector<HTMLElement>("#daily")!;
+const weeklyCounterElem = document.querySelector<HTMLElement>("#weekly-counter")!;
+const weeklyElem = document.querySelector<HTMLElement>("#weekly")!;
+const pbElem = document.querySelector<HTMLElement>("#pb")!;
+const pbTimeElem = document.querySelector<HTMLElement>("#pb-time")!;
+const resetElem = document.querySelector<HTMLElement>("#reset")!;
+const weeklyResetElem = document.querySelector<HTMLElement>("#weekly-reset")!;
+const dailyResetElem = document.querySelector<HTMLElement>("#daily-reset")!;
+const fireteamElem = document.querySelector<HTMLElement>("#fireteam")!;
+const streakElem = document.querySelector<HTMLElement>("#streak")!;
+const currentStreakElem = document.querySelector<HTMLElement>("#current-streak")!;
+const bestStreakElem = document.querySelector<HTMLElement>("#best-streak")!;
+
+const widgetElems: Record<WidgetKind, HTMLElement> = {
+    timer: timerElem,
+    dailyClears: counterElem,
+    weeklyClears: weeklyCounterElem,
+    personalBest: pbElem,
+    streak: streakElem,
+    resetCountdown: resetElem,
+    fireteam: fireteamElem,
+};
+const errorElem = document.querySelector<HTMLElement>("#error")!;
+const errorNetworkElem = document.querySelector<SVGElement>("#error-network")!;
+const errorServerElem = document.querySelector<SVGElement>("#error-server")!;
+const errorMessageElem = document.querySelector<HTMLElement>("#error-message")!;
 
 let currentActivity: CurrentActivity;
+let lastPlayerData: PlayerData | null = null;
+let lastTick: { tick: ActivityTick, receivedAt: number } | null = null;
+let throttledUntil: string | null = null;
 let lastRaidId;
 let doneInitialRefresh = false;
 
@@ -50,6 +80,7 @@ async function init() {
 
     appWindow.listen("preferences_update", (p: TauriEvent<Preferences>) => applyPreferences(p.payload));
     appWindow.listen("playerdata_update", (e: TauriEvent<PlayerDataStatus>) => refresh(e.payload));
+    appWindow.listen("overlay_tick", (e: TauriEvent<OverlayTick>) => overlayTick(e.payload));
 }
 
 async function fetchActivityName(activityHash: number): Promise<string | null> {
@@ -66,8 +97,12 @@ function createPopup(popup: Popup) {
     _createPopup(popup, shown);
 }
 
+function hasWidget(kind: WidgetKind): boolean {
+    return prefs?.overlayWidgets.includes(kind) ?? false;
+}
+
 function checkTimerInterval() {
-    if (!prefs || !shown || !determineActivityType(currentActivity?.activityInfo?.activityModes)) {
+    if (!prefs || !shown || !hasWidget("timer") || !determineActivityType(currentActivity?.activityInfo?.activityModes)) {
         clearTimeout(timerInterval);
         timerInterval = null;
         timerElem.classList.add("hidden");
@@ -81,17 +116,54 @@ function checkTimerInterval() {
     }
 }
 
+function renderError(error: AppError | null | undefined) {
+    errorElem.classList.toggle("hidden", !error && !throttledUntil);
+
+    // Counted down by the overlay tick
+    if (throttledUntil) {
+        errorNetworkElem.classList.add("hidden");
+        errorServerElem.classList.remove("hidden");
+        errorMessageElem.innerText = `Retrying in ${secondsUntil(throttledUntil)}s`;
+        return;
+    }
+
+    if (!error) {
+        return;
+    }
+
+    // Connection problems get a wifi icon, anything Bungie or the app returned gets a server icon
+    errorNetworkElem.classList.toggle("hidden", error.source != "network");
+    errorServerElem.classList.toggle("hidden", error.source == "network");
+    errorMessageElem.innerText = error.source == "network" ? "Offline" : errorCodeLabel(error);
+}
+
+function errorCodeLabel(error: AppError): string {
+    if (error.errorCode != null) {
+        return `Bungie error ${error.errorCode}`;
+    }
+
+    if (error.statusCode != null) {
+        return `Bungie HTTP ${error.statusCode}`;
+    }
+
+    return error.source == "app" ? "Error" : "Bungie error";
+}
+
 function refresh(playerDataStatus: PlayerDataStatus) {
     let playerData = playerDataStatus?.lastUpdate;
 
+    throttledUntil = playerDataStatus?.throttledUntil ?? null;
+    renderError(playerDataStatus?.error);
+
     if (!playerData) {
         widgetContentElem.classList.add("hidden");
 
         currentActivity = null;
+        lastPlayerData = null;
         doneInitialRefresh = false;
 
         if (playerDataStatus?.error) {
-            createPopup({ title: "Failed to fetch initial stats", subtext: playerDataStatus.error });
+            createPopup({ title: "Failed to fetch initial stats", subtext: playerDataStatus.error.message });
         }
 
         return;
@@ -100,12 +172,24 @@ function refresh(playerDataStatus: PlayerDataStatus) {
     widgetContentElem.classList.remove("hidden");
 
     currentActivity = playerData.currentActivity;
+    lastPlayerData = playerData;
 
     checkTimerInterval();
-
-    dailyElem.innerText = String(countDailyClears(playerData.activityHistory));
-
-    let latestRaid = playerData.activityHistory[0];
+    renderFireteam();
+    renderPhase();
+    renderPersonalBest();
+    orbitElem.classList.toggle("hidden", currentActivity.state != "orbit");
+
+    dailyElem.innerText = String(playerData.trackedDailyClears);
+    weeklyElem.innerText = String(playerData.trackedWeeklyClears);
+    currentStreakElem.innerText = `${playerData.currentStreak} day streak`;
+    bestStreakElem.innerText = ` (best ${playerData.bestStreak})`;
+
+    // History order follows the sorting preferences, so find the newest activity explicitly
+    let latestRaid = playerData.activityHistory.reduce<CompletedActivity | undefined>(
+        (latest, a) => !latest || new Date(a.period) > new Date(latest.period) ? a : latest,
+        undefined
+    );
 
     if (doneInitialRefresh && latestRaid?.completed && lastRaidId != latestRaid.instanceId && prefs.displayClearNotifications) {
         const type = determineActivityType(latestRaid.modes);
@@ -146,11 +230,7 @@ function applyPreferences(p: Preferences) {
         sorting: { ...defaults.sorting, ...p.sorting }
     };
 
-    if (prefs.displayDailyClears) {
-        counterElem.classList.remove("hidden");
-    } else {
-        counterElem.classList.add("hidden");
-    }
+    applyWidgets();
 
     if (prefs.displayMilliseconds) {
         msElem.classList.remove("hidden");
@@ -188,29 +268,133 @@ function applyPreferences(p: Preferences) {
         timerElem.style.padding = "4px 8px";
         timerElem.style.marginBottom = "4px";
 
-        counterElem.style.backgroundColor = prefs.colors.textBackgroundColor;
-        counterElem.style.borderRadius = "4px";
-        counterElem.style.padding = "4px 8px";
+        for (const elem of [counterElem, weeklyCounterElem, pbElem, resetElem, streakElem]) {
+            elem.style.backgroundColor = prefs.colors.textBackgroundColor;
+            elem.style.borderRadius = "4px";
+            elem.style.padding = "4px 8px";
+        }
     } else {
         timerElem.style.backgroundColor = "transparent";
         timerElem.style.borderRadius = "";
         timerElem.style.padding = "";
         timerElem.style.marginBottom = "";
 
-        counterElem.style.backgroundColor = "transparent";
-        counterElem.style.borderRadius = "";
-        counterElem.style.padding = "";
+        for (const elem of [counterElem, weeklyCounterElem, pbElem, resetElem, streakElem]) {
+            elem.style.backgroundColor = "transparent";
+            elem.style.borderRadius = "";
+            elem.style.padding = "";
+        }
     }
 
     widgetElem.style.backgroundColor = "transparent";
     widgetContentElem.style.backgroundColor = "transparent";
 
+    applyOverlayPosition(prefs.overlayPosition);
+
     clearTimeout(timerInterval);
     timerInterval = null;
 
     checkTimerInterval();
 }
 
+// Shows the chosen widgets in the chosen order. The timer, fireteam and PB also depend on the
+// current activity, so their render functions decide whether they're actually visible.
+function applyWidgets() {
+    for (const kind of prefs.overlayWidgets) {
+        widgetContentElem.appendChild(widgetElems[kind]);
+
+        if (kind == "timer") {
+            widgetContentElem.appendChild(orbitElem);
+        }
+    }
+
+    for (const kind of ["dailyClears", "weeklyClears", "streak", "resetCountdown"] as WidgetKind[]) {
+        widgetElems[kind].classList.toggle("hidden", !hasWidget(kind));
+    }
+
+    renderFireteam();
+    renderPersonalBest();
+}
+
+function renderPersonalBest() {
+    const pb = currentActivity?.state == "inActivity"
+        ? lastPlayerData?.personalBests[currentActivity.activityHash]
+        : undefined;
+
+    pbElem.classList.toggle("hidden", !hasWidget("personalBest") || !pb);
+    pbTimeElem.textContent = pb?.activityDuration ?? "";
+}
+
+function applyOverlayPosition(position: OverlayPosition) {
+    const isCustom = position.type === "custom";
+    const isRight = position.type === "topRight" || position.type === "bottomRight";
+    const isBottom = position.type === "bottomLeft" || position.type === "bottomRight";
+
+    // A compact overlay window is already placed by the backend, so custom offsets only apply fullscreen
+    const offsetX = isCustom && !prefs.compactOverlay ? position.x : 0;
+    const offsetY = isCustom && !prefs.compactOverlay ? position.y : 0;
+
+    widgetElem.style.left = isRight ? "" : `${offsetX}px`;
+    widgetElem.style.right = isRight ? "0" : "";
+    widgetElem.style.top = isBottom ? "" : `${offsetY}px`;
+    widgetElem.style.bottom = isBottom ? "0" : "";
+
+    // Scale towards the anchored corner so the widget stays in place
+    widgetElem.style.transformOrigin = `${isBottom ? "bottom" : "top"} ${isRight ? "right" : "left"}`;
+    widgetElem.style.transform = `scale(${prefs.overlayScale})`;
+}
+
+// Solo players only see themselves in the party, so there's nothing worth showing
+function renderFireteam() {
+    const members = currentActivity?.fireteam ?? [];
+
+    if (!hasWidget("fireteam") || members.length < 2) {
+        fireteamElem.classList.add("hidden");
+        return;
+    }
+
+    fireteamElem.replaceChildren(...members.map(m => {
+        const elem = document.createElement("div");
+        elem.textContent = m.displayName;
+        return elem;
+    }));
+
+    fireteamElem.classList.remove("hidden");
+}
+
+function renderPhase() {
+    const phase = currentActivity?.phase;
+
+    phaseElem.classList.toggle("hidden", !phase);
+    phaseElem.textContent = phase ? ` · ${phase.current}/${phase.total}` : "";
+}
+
+// Only touches locally computed values, the rest waits for the next playerdata update
+function overlayTick(tick: OverlayTick) {
+    if (tick.activity) {
+        lastTick = { tick: tick.activity, receivedAt: Date.now() };
+    }
+
+    weeklyResetElem.textContent = `Reset in ${formatCountdown(tick.millisUntilWeeklyReset)}`;
+    dailyResetElem.textContent = ` (daily ${formatCountdown(tick.millisUntilDailyReset)})`;
+
+    if (throttledUntil) {
+        errorMessageElem.innerText = `Retrying in ${secondsUntil(throttledUntil)}s`;
+    }
+}
+
+function formatCountdown(millis: number): string {
+    const minutes = Math.max(0, Math.floor(millis / 60000));
+    const days = Math.floor(minutes / 1440);
+    const hours = Math.floor(minutes / 60) % 24;
+
+    if (days > 0) {
+        return `${days}d ${hours}h`;
+    }
+
+    return hours > 0 ? `${hours}h ${minutes % 60}m` : `${minutes % 60}m`;
+}
+
 function timerTick() {
     if (!currentActivity || !currentActivity.startDate) {
         timeElem.textContent = "00:00";
@@ -218,7 +402,10 @@ function timerTick() {
         return;
     }
 
-    let millis = Number(new Date()) - Number(new Date(currentActivity.startDate));
+    // Interpolate from the backend's last tick, falling back to the start date until one arrives
+    let millis = lastTick && lastTick.tick.activityHash == currentActivity.activityHash
+        ? lastTick.tick.elapsedMillis + (Date.now() - lastTick.receivedAt)
+        : Number(new Date()) - Number(new Date(currentActivity.startDate));
     timeElem.textContent = formatTime(millis);
     msElem.textContent = formatMillis(millis);
 }